@@ -0,0 +1,99 @@
+//! Saved `calc` invocations, stored as JSON or TOML
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+use crate::models::RateOverride;
+
+/// A calculation saved to a file
+///
+/// ```toml
+/// resource = "Oxygen"
+/// rate = 2.0
+/// verbose = true
+///
+/// [preferences]
+/// exclude_dlc = "true"
+/// prefer_category = "Oxygen"
+///
+/// [overrides]
+/// "Electrolyzer:Water" = 1.2          # input
+/// "Electrolyzer:Oxygen:output" = 0.9
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct CalcRequest {
+    pub resource: String,
+    #[serde(default = "default_rate")]
+    pub rate: f64,
+    #[serde(default)]
+    pub verbose: bool,
+    /// `calc` flags by long name: exclude_dlc, ceil, prefer_category, compact, scale
+    #[serde(default)]
+    pub preferences: HashMap<String, String>,
+    /// Rates keyed by `BUILDING:RESOURCE` (an input) or `BUILDING:RESOURCE:output`
+    #[serde(default)]
+    pub overrides: HashMap<String, f64>,
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+
+impl CalcRequest {
+    /// Read a request, as JSON if the extension is `.json` and TOML otherwise
+    pub fn load(path: &Path) -> Result<CalcRequest> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let request = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)?
+        } else {
+            toml::from_str(&text)?
+        };
+        Ok(request)
+    }
+
+    /// Boolean preference; absent means false
+    pub fn flag(&self, name: &str) -> Result<bool> {
+        match self.preferences.get(name).map(String::as_str) {
+            None | Some("false") => Ok(false),
+            Some("true") => Ok(true),
+            Some(other) => Err(anyhow!(
+                "preference {}: expected true or false, got '{}'",
+                name,
+                other
+            )),
+        }
+    }
+
+    /// The `overrides` table as rate overrides
+    pub fn rate_overrides(&self) -> Result<Vec<RateOverride>> {
+        self.overrides
+            .iter()
+            .map(|(key, &rate)| {
+                let parts: Vec<&str> = key.split(':').collect();
+                let (building, resource, direction) = match parts[..] {
+                    [building, resource] => (building, resource, "input"),
+                    [building, resource, direction @ ("input" | "output")] => {
+                        (building, resource, direction)
+                    }
+                    _ => {
+                        return Err(anyhow!(
+                            "override '{}': expected BUILDING:RESOURCE[:input|:output]",
+                            key
+                        ));
+                    }
+                };
+                Ok(RateOverride {
+                    building_id: building.to_string(),
+                    resource_id: resource.to_string(),
+                    direction: direction.to_string(),
+                    rate_kg_per_s: rate,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,10 +1,102 @@
 //! Production chain calculator logic
 
-use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
 use rusqlite::Connection;
+use serde::Serialize;
 
 use crate::db;
-use crate::models::{InputRequirement, ProductionNode};
+use crate::models::{
+    Building, BuildingInput, BuildingOutputType, CYCLE_BREAK_ID, HypotheticalField,
+    HypotheticalOverride, InputRequirement, METADATA_BYPRODUCT_FUEL, METADATA_CATEGORY,
+    METADATA_CRITTERS, METADATA_DLC, METADATA_DUPE_OPERATED, METADATA_OVERRIDE, ProducerOrder,
+    ProductionChainError, ProductionForest, ProductionNode, RAW_RESOURCE_ID, Recipe, ResourceState,
+};
+
+/// Options controlling how a production chain is calculated
+#[derive(Debug, Clone, Default)]
+pub struct CalcOptions {
+    /// Only consider base-game buildings as producers
+    pub exclude_dlc: bool,
+    /// Round building counts up to whole buildings
+    pub round_up: bool,
+    /// Pick producers in this category first when several make a resource
+    pub prefer_category: Option<String>,
+    /// Producer building ID to use for a resource, overriding the normal choice
+    pub fixed_producers: HashMap<String, String>,
+    /// Use extractors (Oil Well) as upstream producers; otherwise their outputs count as raw inputs
+    pub include_extractors: bool,
+    /// Skip producers that need a duplicant working them
+    pub exclude_dupe_operated: bool,
+    /// Building stats to shadow for this calculation only
+    pub hypotheticals: Vec<HypotheticalOverride>,
+    /// How to choose between several producers of a resource
+    pub strategy: ProducerStrategy,
+}
+
+/// How `calculate_chain_recursive` picks a producer when several buildings make a resource
+///
+/// `fixed_producers` still takes precedence for the resources it names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ProducerStrategy {
+    /// The first producer the database returns
+    #[default]
+    FirstFound,
+    /// The smallest consumer or largest generator of power
+    MinPower,
+    /// The producer with the least heat output
+    MinHeat,
+    /// The producer with the highest output rate per building
+    MaxOutputRate,
+    /// This building ID if it produces the resource, otherwise the first found
+    UserPreferred(String),
+}
+
+impl ProducerStrategy {
+    /// Parse `first-found`, `min-power`, `min-heat` or `max-output-rate`; anything else is a building ID
+    pub fn parse(s: &str) -> std::result::Result<Self, String> {
+        Ok(match s {
+            "first-found" => ProducerStrategy::FirstFound,
+            "min-power" => ProducerStrategy::MinPower,
+            "min-heat" => ProducerStrategy::MinHeat,
+            "max-output-rate" => ProducerStrategy::MaxOutputRate,
+            "" => return Err("expected a strategy or building ID".to_string()),
+            id => ProducerStrategy::UserPreferred(id.to_string()),
+        })
+    }
+
+    /// Reorder `producers` so the preferred one comes first (stable for ties)
+    fn sort(&self, producers: &mut [(Building, f64)]) {
+        match self {
+            ProducerStrategy::FirstFound => {}
+            ProducerStrategy::MinPower => {
+                producers.sort_by(|a, b| b.0.power_watts.total_cmp(&a.0.power_watts))
+            }
+            ProducerStrategy::MinHeat => {
+                producers.sort_by(|a, b| a.0.heat_output_dtu.total_cmp(&b.0.heat_output_dtu))
+            }
+            ProducerStrategy::MaxOutputRate => producers.sort_by(|a, b| b.1.total_cmp(&a.1)),
+            ProducerStrategy::UserPreferred(id) => producers.sort_by_key(|(b, _)| b.id != *id),
+        }
+    }
+}
+
+/// Length of one game cycle in seconds
+const CYCLE_LENGTH_S: f64 = 600.0;
+
+/// What `optimize` minimizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OptimizeObjective {
+    /// Absolute net power
+    Power,
+    /// Total heat output
+    Heat,
+    /// Whole buildings needed
+    BuildingCount,
+    /// Total construction material mass
+    ConstructionCost,
+}
 
 /// Calculate the production chain for a target resource at a given rate
 ///
@@ -14,96 +106,529 @@ pub fn calculate_production_chain(
     conn: &Connection,
     target_resource: &str,
     target_rate_kg_s: f64,
-) -> Result<ProductionNode> {
-    calculate_chain_recursive(conn, target_resource, target_rate_kg_s, 0)
+    options: &CalcOptions,
+) -> Result<ProductionNode, ProductionChainError> {
+    calculate_production_chain_with_recipe(conn, target_resource, target_rate_kg_s, None, options)
 }
 
+/// Calculate the production chain, making the target with the recipe named `recipe`
+///
+/// The recipe (matched by name, case-insensitive) must output the target
+/// resource; its building and recipe inputs then replace the target's usual
+/// producer and `building_inputs`. Without a matching recipe this is the same
+/// as `calculate_production_chain`. Upstream resources never use recipes.
+pub fn calculate_production_chain_with_recipe(
+    conn: &Connection,
+    target_resource: &str,
+    target_rate_kg_s: f64,
+    recipe: Option<&str>,
+    options: &CalcOptions,
+) -> Result<ProductionNode, ProductionChainError> {
+    let mut root = calculate_chain_recursive(
+        conn,
+        target_resource,
+        target_rate_kg_s,
+        recipe,
+        options,
+        &mut HashSet::new(),
+    )?;
+    root.merge_duplicates(options.round_up);
+    Ok(root)
+}
+
+/// Calculate independent production chains for several `(resource, rate)` targets
+///
+/// Fails on the first target that cannot be calculated.
+pub fn calculate_multi_target(
+    conn: &Connection,
+    targets: &[(String, f64)],
+    options: &CalcOptions,
+) -> Result<ProductionForest, ProductionChainError> {
+    let roots = targets
+        .iter()
+        .map(|(resource, rate)| calculate_production_chain(conn, resource, *rate, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ProductionForest {
+        roots,
+        targets: targets.to_vec(),
+    })
+}
+
+/// Highest rate of `resource` whose chain consumes at most `max_watts`, with that chain
+///
+/// Binary-searches rates between 0 and `max_rate` kg/s, so the result is within
+/// a few µg/s of the limit. Chains that generate power always fit, giving `max_rate`.
+pub fn calculate_max_rate_within_power(
+    conn: &Connection,
+    resource: &str,
+    max_watts: f64,
+    max_rate: f64,
+    options: &CalcOptions,
+) -> Result<(f64, ProductionNode), ProductionChainError> {
+    let consumption = |chain: &ProductionNode| -total_power(chain);
+
+    let chain = calculate_production_chain(conn, resource, max_rate, options)?;
+    if consumption(&chain) <= max_watts {
+        return Ok((max_rate, chain));
+    }
+
+    let (mut low, mut high) = (0.0, max_rate);
+    for _ in 0..50 {
+        let mid = (low + high) / 2.0;
+        let chain = calculate_production_chain(conn, resource, mid, options)?;
+        if consumption(&chain) <= max_watts {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let chain = calculate_production_chain(conn, resource, low, options)?;
+    Ok((low, chain))
+}
+
+/// `visited` holds the resources being resolved above this one; reaching one of
+/// them again yields a `CYCLE_BREAK_ID` node instead of recursing forever.
 fn calculate_chain_recursive(
     conn: &Connection,
     resource: &str,
     rate: f64,
-    depth: usize,
-) -> Result<ProductionNode> {
-    const MAX_DEPTH: usize = 20; // Prevent infinite recursion
+    recipe_name: Option<&str>,
+    options: &CalcOptions,
+    visited: &mut HashSet<String>,
+) -> Result<ProductionNode, ProductionChainError> {
+    if visited.contains(resource) {
+        return Ok(ProductionNode {
+            building_id: CYCLE_BREAK_ID.to_string(),
+            building_name: format!("{} (cycle)", resource),
+            count: 0.0,
+            raw_count: 0.0,
+            output_rate: 0.0,
+            power_watts: 0.0,
+            heat_dtu: 0.0,
+            recipe: None,
+            inputs: vec![InputRequirement {
+                resource_id: resource.to_string(),
+                rate_kg_per_s: rate,
+                surplus_rate: 0.0,
+                upstream: None,
+            }],
+            metadata: HashMap::new(),
+        });
+    }
 
-    if depth > MAX_DEPTH {
-        return Err(anyhow!(
-            "Maximum recursion depth exceeded - possible cycle in production chain"
-        ));
+    // Find buildings that produce this resource; a matching recipe picks its own building
+    let recipe = match recipe_name {
+        Some(name) => find_recipe_output(conn, name, resource)?,
+        None => None,
+    };
+    let mut producers = match &recipe {
+        Some((recipe, recipe_rate)) => match db::get_building(conn, &recipe.building_id)? {
+            Some(building) => vec![(building, *recipe_rate)],
+            None => Vec::new(),
+        },
+        None => db::get_producers(conn, resource, options.prefer_category.as_deref())?,
+    };
+    if options.exclude_dlc {
+        producers.retain(|(b, _)| b.dlc.is_none());
+    }
+    if !options.include_extractors && !visited.is_empty() {
+        producers.retain(|(b, _)| !b.extractor);
+    }
+    if options.exclude_dupe_operated {
+        producers.retain(|(b, _)| !b.dupe_operated);
+    }
+    options.strategy.sort(&mut producers);
+    if let Some(fixed) = options.fixed_producers.get(resource) {
+        producers.sort_by_key(|(b, _)| b.id != *fixed);
     }
 
-    // Find buildings that produce this resource
-    let producers = db::get_producers(conn, resource)?;
+    if producers.is_empty() && visited.is_empty() {
+        return Err(ProductionChainError::NoProducers {
+            resource: resource.to_string(),
+        });
+    }
 
     if producers.is_empty() {
         // This is a raw resource (no building produces it)
+        let raw = Building::new_raw_resource(resource);
         return Ok(ProductionNode {
-            building_id: "RAW_RESOURCE".to_string(),
-            building_name: format!("{} (raw input)", resource),
+            building_id: raw.id,
+            building_name: raw.name,
             count: 0.0,
+            raw_count: 0.0,
+            output_rate: 0.0,
             power_watts: 0.0,
+            heat_dtu: 0.0,
+            recipe: None,
             inputs: vec![InputRequirement {
                 resource_id: resource.to_string(),
                 rate_kg_per_s: rate,
+                surplus_rate: 0.0,
                 upstream: None,
             }],
+            metadata: HashMap::new(),
         });
     }
 
-    // Use the first producer (TODO: let user choose)
-    let (building, output_rate) = &producers[0];
+    // Use the first producer (--prefer-category reorders them)
+    let (building, extracted_rate) = &producers[0];
+    let mut overridden = Vec::new();
+
+    let mut output_rate = match db::get_rate_override(conn, &building.id, resource, "output")? {
+        Some(rate) => {
+            overridden.push(format!("{} output {} kg/s", resource, rate));
+            rate
+        }
+        None => *extracted_rate,
+    };
+    let mut power_watts = building.power_watts;
+    for hypothetical in options
+        .hypotheticals
+        .iter()
+        .filter(|h| h.building_id == building.id)
+    {
+        match hypothetical.field {
+            HypotheticalField::PowerWatts => power_watts = hypothetical.value,
+            HypotheticalField::OutputRate => output_rate = hypothetical.value,
+        }
+        overridden.push(format!(
+            "hypothetical {} {}",
+            hypothetical.field.as_str(),
+            hypothetical.value
+        ));
+    }
+
+    if output_rate <= 0.0 || !output_rate.is_finite() {
+        return Err(ProductionChainError::InvalidOutputRate {
+            building: building.id.clone(),
+            resource: resource.to_string(),
+            rate: output_rate,
+        });
+    }
 
     // Calculate how many buildings needed
-    let num_buildings = rate / output_rate;
-    let total_power = num_buildings * building.power_watts;
+    let raw_count = rate / output_rate;
+    let num_buildings = if options.round_up {
+        raw_count.ceil()
+    } else {
+        raw_count
+    };
+    let total_power = num_buildings * power_watts;
 
-    // Get inputs for this building
-    let inputs = db::get_building_inputs(conn, &building.id)?;
+    // Get inputs for this building (or the chosen recipe)
+    let inputs = match &recipe {
+        Some((recipe, _)) => db::get_recipe_inputs(conn, recipe.id)?
+            .into_iter()
+            .map(|input| BuildingInput {
+                building_id: building.id.clone(),
+                resource_id: input.resource_id,
+                rate_kg_per_s: input.rate_kg_per_s,
+            })
+            .collect(),
+        None => db::get_building_inputs(conn, &building.id)?,
+    };
 
     // Recursively calculate upstream requirements
+    visited.insert(resource.to_string());
     let mut input_requirements = Vec::new();
     for input in inputs {
-        let required_rate = input.rate_kg_per_s * num_buildings;
+        let input_rate =
+            match db::get_rate_override(conn, &building.id, &input.resource_id, "input")? {
+                Some(rate) => {
+                    overridden.push(format!("{} input {} kg/s", input.resource_id, rate));
+                    rate
+                }
+                None => input.rate_kg_per_s,
+            };
+        let required_rate = input_rate * num_buildings;
 
         // Try to find upstream producer
-        let upstream = match calculate_chain_recursive(conn, &input.resource_id, required_rate, depth + 1) {
+        let chain = calculate_chain_recursive(
+            conn,
+            &input.resource_id,
+            required_rate,
+            None,
+            options,
+            visited,
+        );
+        let upstream = match chain {
             Ok(node) => Some(Box::new(node)),
-            Err(_) => None, // Raw resource or error
+            Err(ProductionChainError::NoProducers { .. }) => None, // Treat as a raw input
+            Err(e) => return Err(e),
+        };
+
+        // Rounded-up upstream buildings make more than this input needs
+        let surplus_rate = match &upstream {
+            Some(node) if node.is_building() => {
+                (node.count * node.output_rate - required_rate).max(0.0)
+            }
+            _ => 0.0,
         };
 
         input_requirements.push(InputRequirement {
             resource_id: input.resource_id,
             rate_kg_per_s: required_rate,
+            surplus_rate,
             upstream,
         });
     }
+    visited.remove(resource);
+
+    let mut metadata = HashMap::new();
+    if let Some(category) = &building.category {
+        metadata.insert(METADATA_CATEGORY.to_string(), category.clone());
+    }
+    if let Some(critters) = building.min_critters {
+        // Every (partial) building still needs its full critter roster
+        let total = critters as f64 * num_buildings.ceil();
+        metadata.insert(
+            METADATA_CRITTERS.to_string(),
+            format!("{} ({} per building)", total, critters),
+        );
+    }
+    if let Some(dlc) = &building.dlc {
+        metadata.insert(METADATA_DLC.to_string(), dlc.clone());
+    }
+    if building.dupe_operated {
+        metadata.insert(METADATA_DUPE_OPERATED.to_string(), "yes".to_string());
+    }
+    if !overridden.is_empty() {
+        metadata.insert(METADATA_OVERRIDE.to_string(), overridden.join(", "));
+    }
 
     Ok(ProductionNode {
         building_id: building.id.clone(),
         building_name: building.name.clone(),
         count: num_buildings,
+        raw_count,
+        output_rate,
         power_watts: total_power,
+        heat_dtu: num_buildings * building.heat_output_dtu,
+        recipe: recipe.map(|(recipe, _)| recipe.name),
         inputs: input_requirements,
+        metadata,
     })
 }
 
+/// The first recipe named `name` that outputs `resource`, with its output rate
+fn find_recipe_output(
+    conn: &Connection,
+    name: &str,
+    resource: &str,
+) -> Result<Option<(Recipe, f64)>> {
+    for recipe in db::get_recipes_by_name(conn, name)? {
+        let outputs = db::get_recipe_outputs(conn, recipe.id)?;
+        if let Some(output) = outputs.iter().find(|o| o.resource_id == resource) {
+            return Ok(Some((recipe, output.rate_kg_per_s)));
+        }
+    }
+    Ok(None)
+}
+
+/// Find the producer choices minimizing `objective`
+///
+/// Starting from the default chain, branches on every resource in the chain
+/// that several buildings can produce, evaluating at most `max_evaluations`
+/// chains (but always the default one). Returns the best chain with its
+/// objective score.
+pub fn optimize(
+    conn: &Connection,
+    target_resource: &str,
+    target_rate_kg_s: f64,
+    objective: OptimizeObjective,
+    options: &CalcOptions,
+    max_evaluations: usize,
+) -> Result<(ProductionNode, f64), ProductionChainError> {
+    let order = match objective {
+        OptimizeObjective::Power => ProducerOrder::PowerEfficiency,
+        OptimizeObjective::Heat => ProducerOrder::HeatOutput,
+        OptimizeObjective::BuildingCount | OptimizeObjective::ConstructionCost => {
+            ProducerOrder::Rate
+        }
+    };
+    let mut best: Option<(ProductionNode, f64)> = None;
+    let mut evaluations = 0;
+    let mut pending = vec![options.clone()];
+
+    while let Some(candidate) = pending.pop() {
+        // The default chain is evaluated even with a budget of 0
+        if best.is_some() && evaluations >= max_evaluations {
+            break;
+        }
+        evaluations += 1;
+
+        let chain =
+            calculate_production_chain(conn, target_resource, target_rate_kg_s, &candidate)?;
+        let score = objective_score(conn, &chain, objective)?;
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_score)| score < *best_score)
+        {
+            best = Some((chain.clone(), score));
+        }
+
+        // Branch on the first resource whose producer hasn't been fixed yet
+        for resource in produced_resources(target_resource, &chain) {
+            if candidate.fixed_producers.contains_key(&resource) {
+                continue;
+            }
+            let mut producers = db::get_producers_ordered_by(conn, &resource, order)?;
+            if candidate.exclude_dlc {
+                producers.retain(|(b, _)| b.dlc.is_none());
+            }
+            if candidate.exclude_dupe_operated {
+                producers.retain(|(b, _)| !b.dupe_operated);
+            }
+            if producers.len() > 1 {
+                // `pending` is a stack: push the most promising producer last so it is tried first
+                for (building, _) in producers.into_iter().rev() {
+                    let mut next = candidate.clone();
+                    next.fixed_producers.insert(resource.clone(), building.id);
+                    pending.push(next);
+                }
+                break;
+            }
+        }
+    }
+
+    // The default chain is always evaluated, so there is a best one
+    Ok(best.expect("optimize evaluates at least one chain"))
+}
+
+/// Resources made by a building somewhere in the chain, root first
+fn produced_resources(target_resource: &str, root: &ProductionNode) -> Vec<String> {
+    let mut produced = vec![target_resource.to_string()];
+    for node in root.iter() {
+        for input in node.filter_produced_inputs() {
+            produced.push(input.resource_id.clone());
+        }
+    }
+    produced
+}
+
+/// Byproducts of the chain's buildings with the buildings that could consume them
+///
+/// A byproduct is a building output that nothing in the chain was built to
+/// produce. Returns `(producer name, byproduct, consumers)`, skipping
+/// byproducts with no consumers.
+pub fn byproduct_consumers(
+    conn: &Connection,
+    target_resource: &str,
+    root: &ProductionNode,
+) -> Result<Vec<(String, String, Vec<Building>)>> {
+    let produced = produced_resources(target_resource, root);
+    let mut seen = Vec::new();
+    let mut loops = Vec::new();
+
+    for node in root.iter().filter(|n| n.is_building()) {
+        if seen.contains(&node.building_id) {
+            continue;
+        }
+        seen.push(node.building_id.clone());
+
+        let Some(full) = db::get_full_building(conn, &node.building_id)? else {
+            continue;
+        };
+        for output in full
+            .outputs
+            .iter()
+            .filter(|o| !produced.contains(&o.resource_id))
+        {
+            let consumers = db::find_buildings_consuming_byproduct(
+                conn,
+                &node.building_id,
+                &output.resource_id,
+            )?;
+            if !consumers.is_empty() {
+                loops.push((
+                    node.building_name.clone(),
+                    output.resource_id.clone(),
+                    consumers,
+                ));
+            }
+        }
+    }
+    Ok(loops)
+}
+
+/// Objective value of a chain; lower is better
+fn objective_score(
+    conn: &Connection,
+    chain: &ProductionNode,
+    objective: OptimizeObjective,
+) -> Result<f64> {
+    let buildings = chain.iter().filter(|n| n.is_building());
+    let score = match objective {
+        OptimizeObjective::Power => total_power(chain).abs(),
+        OptimizeObjective::BuildingCount => chain.total_buildings_ceiling(),
+        OptimizeObjective::Heat => total_heat(chain),
+        OptimizeObjective::ConstructionCost => {
+            let mut mass = 0.0;
+            for node in buildings {
+                mass +=
+                    db::get_building_material_mass(conn, &node.building_id)? * node.count.ceil();
+            }
+            mass
+        }
+    };
+    Ok(score)
+}
+
 /// Calculate total power consumption for an entire production chain
 pub fn total_power(node: &ProductionNode) -> f64 {
-    let mut total = node.power_watts;
-    for input in &node.inputs {
-        if let Some(upstream) = &input.upstream {
-            total += total_power(upstream);
+    node.iter().map(|n| n.power_watts).sum()
+}
+
+/// Calculate total heat output for an entire production chain, in DTU/s
+pub fn total_heat(node: &ProductionNode) -> f64 {
+    node.iter().map(|n| n.heat_dtu).sum()
+}
+
+/// Rate actually produced by the root node of a chain
+///
+/// With `round_up` this exceeds the requested rate; raw inputs are
+/// passed through unchanged.
+pub fn achieved_rate(node: &ProductionNode) -> f64 {
+    if !node.is_building() {
+        node.inputs.iter().map(|i| i.rate_kg_per_s).sum()
+    } else {
+        node.count * node.output_rate
+    }
+}
+
+/// Look up stored descriptions for every building in a chain, keyed by building ID
+pub fn building_notes(conn: &Connection, node: &ProductionNode) -> Result<HashMap<String, String>> {
+    let mut notes = HashMap::new();
+    for n in node.iter() {
+        if n.is_building()
+            && !notes.contains_key(&n.building_id)
+            && let Some(description) = db::get_building_description(conn, &n.building_id)?
+        {
+            notes.insert(n.building_id.clone(), description);
         }
     }
-    total
+    Ok(notes)
 }
 
 /// Format a production chain as a readable string
-pub fn format_production_chain(node: &ProductionNode, indent: usize) -> String {
+///
+/// Buildings with an entry in `notes` get it appended to their line.
+pub fn format_production_chain(
+    node: &ProductionNode,
+    indent: usize,
+    notes: &HashMap<String, String>,
+) -> String {
     let mut output = String::new();
     let prefix = "  ".repeat(indent);
 
-    if node.building_id == "RAW_RESOURCE" {
+    if node.building_id == CYCLE_BREAK_ID {
+        for input in &node.inputs {
+            output.push_str(&format!(
+                "{}⚠ {} @ {:.3} kg/s (cycle: already produced higher up the chain, supply externally)\n",
+                prefix, input.resource_id, input.rate_kg_per_s
+            ));
+        }
+    } else if node.building_id == RAW_RESOURCE_ID {
         for input in &node.inputs {
             output.push_str(&format!(
                 "{}→ {} @ {:.3} kg/s (raw input)\n",
@@ -118,19 +643,57 @@ pub fn format_production_chain(node: &ProductionNode, indent: usize) -> String {
         } else {
             "no power".to_string()
         };
+        let heat_str = if node.heat_dtu != 0.0 {
+            format!(", {:.0} DTU/s", node.heat_dtu)
+        } else {
+            String::new()
+        };
+
+        let note = notes
+            .get(&node.building_id)
+            .map(|n| format!(" - {}", n))
+            .unwrap_or_default();
 
         output.push_str(&format!(
-            "{}{:.2}x {} ({})\n",
-            prefix, node.count, node.building_name, power_str
+            "{}{:.2}x {} ({}{}){}\n",
+            prefix, node.count, node.building_name, power_str, heat_str, note
         ));
 
-        for input in &node.inputs {
+        if let Some(recipe) = &node.recipe {
+            output.push_str(&format!("{}  [recipe: {}]\n", prefix, recipe));
+        }
+
+        let mut metadata: Vec<_> = node
+            .metadata
+            .iter()
+            .filter(|(_, v)| !v.is_empty())
+            .collect();
+        metadata.sort();
+        for (key, value) in metadata {
+            output.push_str(&format!("{}  [{}: {}]\n", prefix, key, value));
+        }
+
+        // Produced inputs first so raw inputs end each building's list
+        for input in node
+            .filter_produced_inputs()
+            .into_iter()
+            .chain(node.filter_raw_inputs())
+        {
+            let surplus = if input.surplus_rate > 1e-9 {
+                format!(
+                    " (surplus {:.3} kg/s, {:.0}% utilized)",
+                    input.surplus_rate,
+                    input.utilization() * 100.0
+                )
+            } else {
+                String::new()
+            };
             output.push_str(&format!(
-                "{}  needs {} @ {:.3} kg/s\n",
-                prefix, input.resource_id, input.rate_kg_per_s
+                "{}  needs {} @ {:.3} kg/s{}\n",
+                prefix, input.resource_id, input.rate_kg_per_s, surplus
             ));
             if let Some(upstream) = &input.upstream {
-                output.push_str(&format_production_chain(upstream, indent + 2));
+                output.push_str(&format_production_chain(upstream, indent + 2, notes));
             }
         }
     }
@@ -138,34 +701,543 @@ pub fn format_production_chain(node: &ProductionNode, indent: usize) -> String {
     output
 }
 
-/// Summary of a production chain calculation
+/// Warn about inputs that would arrive in the wrong state at `temp_c`
+///
+/// Each input is expected in its stored state (e.g. Water as a Liquid); if
+/// the melt/boil points put it in another state at the delivery temperature,
+/// the consuming building can't take it from its upstream producer as-is.
+pub fn state_warnings(
+    conn: &Connection,
+    node: &ProductionNode,
+    temp_c: f64,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    let mut checked: HashMap<String, Option<(ResourceState, ResourceState)>> = HashMap::new();
+
+    for n in node.iter().filter(|n| n.is_building()) {
+        for input in &n.inputs {
+            if !checked.contains_key(&input.resource_id) {
+                let conflict = db::get_resource(conn, &input.resource_id)?.and_then(|resource| {
+                    let expected = ResourceState::from_name(resource.state.as_deref()?)?;
+                    let actual = resource.state_at_temperature(temp_c)?;
+                    (actual != expected).then_some((expected, actual))
+                });
+                checked.insert(input.resource_id.clone(), conflict);
+            }
+
+            if let Some(Some((expected, actual))) = checked.get(&input.resource_id) {
+                let source = match &input.upstream {
+                    Some(upstream) if upstream.is_building() => upstream.building_name.as_str(),
+                    _ => "its source",
+                };
+                warnings.push(format!(
+                    "{} needs {} as a {}, but {} delivers it as a {} at {}°C",
+                    n.building_name,
+                    input.resource_id,
+                    expected.as_str(),
+                    source,
+                    actual.as_str(),
+                    temp_c
+                ));
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Fewest whole cooling buildings absorbing `heat_load_dtu` DTU/s
+///
+/// Coolers with an input that would be in the wrong state at `ambient_temp_c`
+/// (e.g. water frozen solid) are skipped. The strongest coolers are used first
+/// and the remainder goes to the weakest cooler that covers it on its own.
+/// Returns `(building, count)` pairs, empty when no cooler is usable.
+pub fn find_cooling_solution(
+    conn: &Connection,
+    heat_load_dtu: f64,
+    ambient_temp_c: f64,
+) -> Result<Vec<(Building, f64)>> {
+    let mut usable = Vec::new();
+    'coolers: for cooler in db::get_buildings_with_heat_cooling(conn)? {
+        for input in db::get_building_inputs(conn, &cooler.id)? {
+            let Some(resource) = db::get_resource(conn, &input.resource_id)? else {
+                continue;
+            };
+            let expected = resource.state.as_deref().and_then(ResourceState::from_name);
+            let actual = resource.state_at_temperature(ambient_temp_c);
+            if expected.is_some() && actual.is_some() && expected != actual {
+                continue 'coolers;
+            }
+        }
+        usable.push(cooler);
+    }
+
+    // get_buildings_with_heat_cooling sorts most cooling first
+    let mut solution: Vec<(Building, f64)> = Vec::new();
+    let mut remaining = heat_load_dtu;
+    for cooler in &usable {
+        let count = (remaining / -cooler.heat_output_dtu).floor();
+        if count > 0.0 {
+            remaining += count * cooler.heat_output_dtu;
+            solution.push((cooler.clone(), count));
+        }
+    }
+    if remaining > 1e-9 {
+        let last = usable
+            .iter()
+            .rev()
+            .find(|c| -c.heat_output_dtu >= remaining)
+            .or(usable.first());
+        if let Some(cooler) = last {
+            match solution.iter_mut().find(|(b, _)| b.id == cooler.id) {
+                Some(entry) => entry.1 += 1.0,
+                None => solution.push((cooler.clone(), 1.0)),
+            }
+        }
+    }
+    Ok(solution)
+}
+
+/// Net effect of running a production cycle on 1 kg/s of its resource
 #[derive(Debug)]
+pub struct CycleAnalysis {
+    pub resource: String,
+    /// `(building name, count)` per step, in cycle order
+    pub steps: Vec<(String, f64)>,
+    /// Resource returned minus the 1 kg/s fed in
+    pub net_gain_kg_s: f64,
+    pub power_delta_w: f64,
+    /// Inputs the cycle needs from outside, summed per resource
+    pub external_inputs: Vec<(String, f64)>,
+}
+
+impl CycleAnalysis {
+    /// Gains resource or power overall
+    pub fn is_beneficial(&self) -> bool {
+        self.net_gain_kg_s > 1e-9 || self.power_delta_w > 1e-9
+    }
+}
+
+/// Follow a cycle from `db::find_cyclic_chains` with 1 kg/s of `resource` fed in
+///
+/// Returns `None` if a step has no usable rate.
+pub fn analyze_cycle(
+    conn: &Connection,
+    resource: &str,
+    cycle: &[(String, String)],
+) -> Result<Option<CycleAnalysis>> {
+    let mut flow_resource = resource.to_string();
+    let mut flow = 1.0;
+    let mut steps = Vec::new();
+    let mut power = 0.0;
+    let mut external: HashMap<String, f64> = HashMap::new();
+
+    for (building_id, output_resource) in cycle {
+        let Some(full) = db::get_full_building(conn, building_id)? else {
+            return Ok(None);
+        };
+        let in_rate = full
+            .inputs
+            .iter()
+            .find(|i| i.resource_id == flow_resource)
+            .map(|i| i.rate_kg_per_s);
+        let out_rate = full
+            .outputs
+            .iter()
+            .find(|o| o.resource_id == *output_resource)
+            .map(|o| o.rate_kg_per_s);
+        let (Some(in_rate), Some(out_rate)) = (in_rate, out_rate) else {
+            return Ok(None);
+        };
+        if in_rate <= 0.0 {
+            return Ok(None);
+        }
+
+        let count = flow / in_rate;
+        for input in full
+            .inputs
+            .iter()
+            .filter(|i| i.resource_id != flow_resource)
+        {
+            *external.entry(input.resource_id.clone()).or_default() += input.rate_kg_per_s * count;
+        }
+        power += full.building.power_watts * count;
+        steps.push((full.building.name, count));
+
+        flow = out_rate * count;
+        flow_resource = output_resource.clone();
+    }
+
+    let mut external_inputs: Vec<_> = external.into_iter().collect();
+    external_inputs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(Some(CycleAnalysis {
+        resource: resource.to_string(),
+        steps,
+        net_gain_kg_s: flow - 1.0,
+        power_delta_w: power,
+        external_inputs,
+    }))
+}
+
+/// Per-duplicant demand used by `find_self_sustaining_network`
+#[derive(Debug, Clone)]
+pub struct ColonyRates {
+    pub oxygen_kg_s: f64,
+    pub water_kg_s: f64,
+    pub kcal_per_cycle: f64,
+    /// Food to produce; defaults to the first known food with a producer
+    pub food: Option<String>,
+}
+
+/// Buildings keeping a colony supplied with oxygen, water, food and power
+#[derive(Debug)]
+pub struct ColonyNetwork {
+    pub dupe_count: u32,
+    /// `(resource, rate, chain)` per demand
+    pub chains: Vec<(String, f64, ProductionNode)>,
+    /// Generators covering the chains' power deficit, with their fuel chains
+    pub generator: Option<ProductionNode>,
+    /// Demands that could not be calculated, with the reason
+    pub unmet: Vec<String>,
+}
+
+impl ColonyNetwork {
+    pub fn net_power(&self) -> f64 {
+        self.chains
+            .iter()
+            .map(|(_, _, chain)| total_power(chain))
+            .sum::<f64>()
+            + self.generator.as_ref().map(total_power).unwrap_or(0.0)
+    }
+
+    /// Net power ≥ 0 and every demand met
+    pub fn is_self_sustaining(&self) -> bool {
+        self.unmet.is_empty() && self.net_power() >= -1e-6
+    }
+
+    /// Combined summary of every chain, generators included
+    pub fn summary(&self) -> ChainSummary {
+        let mut summaries: Vec<_> = self
+            .chains
+            .iter()
+            .map(|(resource, rate, chain)| summarize_chain(chain, resource, *rate))
+            .collect();
+        if let Some(generator) = &self.generator {
+            summaries.push(summarize_chain(generator, "Power", 0.0));
+        }
+        merge_summaries(&summaries)
+    }
+}
+
+/// Calculate the buildings `dupe_count` duplicants need, powered by the chains themselves
+///
+/// Chains oxygen, water and food at the per-dupe `rates`, then sizes a
+/// generator for any power deficit. Generators whose fuel the chains already
+/// make as a byproduct (Hydrogen from an Electrolyzer) are preferred and use
+/// that byproduct before anything new is built for their fuel.
+pub fn find_self_sustaining_network(
+    conn: &Connection,
+    dupe_count: u32,
+    rates: &ColonyRates,
+) -> Result<ColonyNetwork> {
+    const MAX_ITERATIONS: usize = 20;
+
+    let dupes = dupe_count as f64;
+    let options = CalcOptions::default();
+    let mut demands = vec![("Oxygen".to_string(), rates.oxygen_kg_s * dupes)];
+    if rates.water_kg_s > 0.0 {
+        demands.push(("Water".to_string(), rates.water_kg_s * dupes));
+    }
+
+    let mut unmet = Vec::new();
+    let mut food = None;
+    for (id, calories) in db::list_foods(conn)? {
+        let Some(calories) = calories.filter(|c| *c > 0.0) else {
+            continue;
+        };
+        let wanted = rates.food.as_ref().is_none_or(|f| *f == id);
+        if wanted && (rates.food.is_some() || !db::get_producers(conn, &id, None)?.is_empty()) {
+            food = Some((id, calories));
+            break;
+        }
+    }
+    match food {
+        // Calories are per unit (kg); a duplicant eats `kcal_per_cycle` every cycle
+        Some((id, calories)) => {
+            demands.push((id, rates.kcal_per_cycle * dupes / calories / CYCLE_LENGTH_S))
+        }
+        None => unmet.push(match &rates.food {
+            Some(f) => format!("Food: no calories known for '{}'", f),
+            None => "Food: no food with known calories has a producer".to_string(),
+        }),
+    }
+
+    let mut chains = Vec::new();
+    let mut byproducts: HashMap<String, f64> = HashMap::new();
+    for (resource, rate) in demands {
+        match calculate_production_chain(conn, &resource, rate, &options) {
+            Ok(chain) => {
+                let produced = produced_resources(&resource, &chain);
+                for node in chain.iter().filter(|n| n.is_building()) {
+                    for output in db::get_full_building(conn, &node.building_id)?
+                        .map(|f| f.outputs)
+                        .unwrap_or_default()
+                    {
+                        if !produced.contains(&output.resource_id) {
+                            *byproducts.entry(output.resource_id).or_default() +=
+                                output.rate_kg_per_s * node.count;
+                        }
+                    }
+                }
+                chains.push((resource, rate, chain));
+            }
+            Err(ProductionChainError::DatabaseError(e)) => return Err(e.into()),
+            Err(ProductionChainError::Other(e)) => return Err(e),
+            Err(e) => unmet.push(format!("{}: {}", resource, e)),
+        }
+    }
+
+    let mut network = ColonyNetwork {
+        dupe_count,
+        chains,
+        generator: None,
+        unmet,
+    };
+    if network.net_power() >= 0.0 {
+        return Ok(network);
+    }
+
+    // Fuel-burning generators, most byproduct-fed first, then the most powerful
+    let mut generators = Vec::new();
+    for building in db::list_buildings(conn)?
+        .into_iter()
+        .filter(|b| b.power_watts > 0.0)
+    {
+        let inputs = db::get_building_inputs(conn, &building.id)?;
+        if inputs.is_empty() {
+            continue; // Solar panels and manual generators need things the calculator doesn't model
+        }
+        let fed = inputs
+            .iter()
+            .filter(|i| byproducts.contains_key(&i.resource_id))
+            .count();
+        generators.push((fed as f64 / inputs.len() as f64, building, inputs));
+    }
+    generators.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then(b.1.power_watts.total_cmp(&a.1.power_watts))
+    });
+    let Some((_, generator, fuel)) = generators.into_iter().next() else {
+        network
+            .unmet
+            .push("Power: no fuel-burning generator in the database".to_string());
+        return Ok(network);
+    };
+
+    let mut count = 0.0;
+    for _ in 0..MAX_ITERATIONS {
+        let deficit = -network.net_power();
+        if deficit <= 1e-6 {
+            break;
+        }
+        count += deficit / generator.power_watts;
+
+        let mut inputs = Vec::new();
+        let mut from_byproducts = Vec::new();
+        for input in &fuel {
+            let needed = input.rate_kg_per_s * count;
+            let available = byproducts.get(&input.resource_id).copied().unwrap_or(0.0);
+            if available > 0.0 {
+                from_byproducts.push(format!(
+                    "{} {:.3} kg/s",
+                    input.resource_id,
+                    needed.min(available)
+                ));
+            }
+            let remaining = needed - available;
+            if remaining <= 0.0 {
+                continue;
+            }
+            // Resolve fuel as an upstream input so unproducible fuel becomes a raw input
+            let mut visited = HashSet::from(["Power".to_string()]);
+            let chain = calculate_chain_recursive(
+                conn,
+                &input.resource_id,
+                remaining,
+                None,
+                &options,
+                &mut visited,
+            );
+            let upstream = match chain {
+                Ok(node) => Some(Box::new(node)),
+                Err(ProductionChainError::DatabaseError(e)) => return Err(e.into()),
+                Err(ProductionChainError::Other(e)) => return Err(e),
+                Err(_) => None,
+            };
+            inputs.push(InputRequirement {
+                resource_id: input.resource_id.clone(),
+                rate_kg_per_s: remaining,
+                surplus_rate: 0.0,
+                upstream,
+            });
+        }
+
+        let mut metadata = HashMap::new();
+        if let Some(category) = &generator.category {
+            metadata.insert(METADATA_CATEGORY.to_string(), category.clone());
+        }
+        if !from_byproducts.is_empty() {
+            metadata.insert(
+                METADATA_BYPRODUCT_FUEL.to_string(),
+                from_byproducts.join(", "),
+            );
+        }
+        network.generator = Some(ProductionNode {
+            building_id: generator.id.clone(),
+            building_name: generator.name.clone(),
+            count,
+            raw_count: count,
+            output_rate: 0.0,
+            power_watts: count * generator.power_watts,
+            heat_dtu: count * generator.heat_output_dtu,
+            recipe: None,
+            inputs,
+            metadata,
+        });
+    }
+
+    Ok(network)
+}
+
+impl std::fmt::Display for ColonyNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "=== Colony Network ({} duplicants) ===", self.dupe_count)?;
+        for (resource, rate, chain) in &self.chains {
+            writeln!(
+                f,
+                "{} @ {:.3} kg/s: {:.0}W",
+                resource,
+                rate,
+                total_power(chain)
+            )?;
+        }
+        if let Some(generator) = &self.generator {
+            writeln!(
+                f,
+                "Power: {:.2}x {} generating {:.0}W",
+                generator.count, generator.building_name, generator.power_watts
+            )?;
+        }
+        for reason in &self.unmet {
+            writeln!(f, "Unmet: {}", reason)?;
+        }
+        writeln!(
+            f,
+            "Self-sustaining: {} (net {:.0}W)",
+            if self.is_self_sustaining() {
+                "yes"
+            } else {
+                "no"
+            },
+            self.net_power()
+        )
+    }
+}
+
+/// Summary of a production chain calculation
+#[derive(Debug, Serialize)]
 pub struct ChainSummary {
     pub target_resource: String,
     pub target_rate: f64,
+    pub targets: Vec<(String, f64)>, // Every (resource, kg/s) target; one entry unless merged
     pub total_power_consumption: f64,
     pub total_power_generation: f64,
     pub net_power: f64,
-    pub building_counts: Vec<(String, f64)>,
+    pub total_heat_output_dtu: f64, // Negative when the chain's coolers outweigh its heaters
+    pub building_counts: Vec<(String, f64, f64)>, // (name, raw count, count as built; whole when rounded_up)
+    pub rounded_up: bool, // Building counts were rounded up to whole buildings (`CalcOptions::round_up`)
     pub raw_inputs: Vec<(String, f64)>,
+    pub byproduct_credits: Vec<(String, f64)>, // Raw input kg/s covered by chain surplus, see credit_byproducts
+}
+
+impl ChainSummary {
+    /// Fingerprint of the buildings and raw inputs, for telling whether a rerun changed the result
+    ///
+    /// Counts and rates are rounded to 3 decimal places so float noise doesn't change the hash.
+    /// Uses 64-bit FNV-1a so the value stays the same across Rust releases.
+    pub fn hash(&self) -> u64 {
+        let rounded = |x: f64| (x * 1000.0).round() as i64;
+        let mut hasher = Fnv1a::default();
+        for (name, raw, whole) in &self.building_counts {
+            hasher.write_str(name);
+            hasher.write(&rounded(*raw).to_le_bytes());
+            hasher.write(&rounded(*whole).to_le_bytes());
+        }
+        for (name, rate) in &self.raw_inputs {
+            hasher.write_str(name);
+            hasher.write(&rounded(*rate).to_le_bytes());
+        }
+        hasher.0
+    }
+}
+
+/// 64-bit FNV-1a, for hashes that are printed and compared between runs
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// Write the string followed by a 0xff terminator, so ("ab", "c") and ("a", "bc") differ
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+        self.write(&[0xff]);
+    }
 }
 
 /// Generate a summary of the production chain
-pub fn summarize_chain(node: &ProductionNode, target_resource: &str, target_rate: f64) -> ChainSummary {
-    let mut building_counts: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
-    let mut raw_inputs: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
+pub fn summarize_chain(
+    node: &ProductionNode,
+    target_resource: &str,
+    target_rate: f64,
+) -> ChainSummary {
+    let mut building_counts: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut raw_inputs: HashMap<String, f64> = HashMap::new();
     let mut power_consumption = 0.0;
     let mut power_generation = 0.0;
+    let mut heat_output = 0.0;
+
+    for n in node.iter().filter(|n| n.is_building()) {
+        let counts = building_counts.entry(n.building_name.clone()).or_default();
+        counts.0 += n.raw_count;
+        counts.1 += n.count;
 
-    collect_summary(
-        node,
-        &mut building_counts,
-        &mut raw_inputs,
-        &mut power_consumption,
-        &mut power_generation,
-    );
+        if n.power_watts < 0.0 {
+            power_consumption += -n.power_watts;
+        } else {
+            power_generation += n.power_watts;
+        }
+        heat_output += n.heat_dtu;
 
-    let mut building_list: Vec<_> = building_counts.into_iter().collect();
+        for input in n.filter_raw_inputs() {
+            *raw_inputs.entry(input.resource_id.clone()).or_default() += input.rate_kg_per_s;
+        }
+    }
+
+    let mut building_list: Vec<_> = building_counts
+        .into_iter()
+        .map(|(name, (raw, whole))| (name, raw, whole))
+        .collect();
     building_list.sort_by(|a, b| a.0.cmp(&b.0));
 
     let mut raw_list: Vec<_> = raw_inputs.into_iter().collect();
@@ -174,54 +1246,151 @@ pub fn summarize_chain(node: &ProductionNode, target_resource: &str, target_rate
     ChainSummary {
         target_resource: target_resource.to_string(),
         target_rate,
+        targets: vec![(target_resource.to_string(), target_rate)],
         total_power_consumption: power_consumption,
         total_power_generation: power_generation,
         net_power: power_generation - power_consumption,
+        total_heat_output_dtu: heat_output,
         building_counts: building_list,
+        // Nodes only differ from their raw count when round_up ceiled them
+        rounded_up: node.iter().any(|n| n.count != n.raw_count),
         raw_inputs: raw_list,
+        byproduct_credits: Vec::new(),
     }
 }
 
-fn collect_summary(
-    node: &ProductionNode,
-    buildings: &mut std::collections::HashMap<String, f64>,
-    raw_inputs: &mut std::collections::HashMap<String, f64>,
-    power_consumption: &mut f64,
-    power_generation: &mut f64,
-) {
-    if node.building_id == "RAW_RESOURCE" {
+/// Reduce `summary`'s raw inputs by what the chain makes but doesn't use
+///
+/// Surplus is every building's outputs other than the resource it was built
+/// for (the Electrolyzer's Hydrogen in an Oxygen chain), plus the extra made
+/// by rounded-up buildings. The credited amounts are recorded in
+/// `byproduct_credits`.
+pub fn credit_byproducts(
+    conn: &Connection,
+    summary: &mut ChainSummary,
+    chain: &ProductionNode,
+) -> Result<()> {
+    let mut surplus: HashMap<String, f64> = HashMap::new();
+    let mut pending = vec![(chain, summary.target_resource.clone())];
+    while let Some((node, produces)) = pending.pop() {
         for input in &node.inputs {
-            *raw_inputs.entry(input.resource_id.clone()).or_default() += input.rate_kg_per_s;
+            if let Some(upstream) = input.upstream.as_deref().filter(|u| u.is_building()) {
+                *surplus.entry(input.resource_id.clone()).or_default() += input.surplus_rate;
+                pending.push((upstream, input.resource_id.clone()));
+            }
         }
-    } else {
-        *buildings.entry(node.building_name.clone()).or_default() += node.count;
+        if !node.is_building() {
+            continue;
+        }
+        let Some(full) = db::get_full_building(conn, &node.building_id)? else {
+            continue;
+        };
+        let byproducts = full
+            .outputs
+            .iter()
+            .filter(|o| o.output_type == BuildingOutputType::Mass && o.resource_id != produces);
+        for output in byproducts {
+            *surplus.entry(output.resource_id.clone()).or_default() +=
+                output.rate_kg_per_s * node.count;
+        }
+    }
 
-        if node.power_watts < 0.0 {
-            *power_consumption += -node.power_watts;
-        } else {
-            *power_generation += node.power_watts;
+    for (resource, rate) in &mut summary.raw_inputs {
+        let Some(available) = surplus.get_mut(resource.as_str()) else {
+            continue;
+        };
+        let credit = available.min(*rate);
+        if credit > 0.0 {
+            *rate -= credit;
+            *available -= credit;
+            summary.byproduct_credits.push((resource.clone(), credit));
         }
+    }
+    summary.raw_inputs.retain(|(_, rate)| *rate > 1e-9);
+    Ok(())
+}
 
-        for input in &node.inputs {
-            if let Some(upstream) = &input.upstream {
-                collect_summary(upstream, buildings, raw_inputs, power_consumption, power_generation);
-            } else {
-                // No upstream producer - this is a raw input
-                *raw_inputs.entry(input.resource_id.clone()).or_default() += input.rate_kg_per_s;
-            }
+/// Combine the summaries of several independent chains into one
+///
+/// Building counts, raw inputs and power are summed; the target becomes
+/// the `+`-joined resource names with the total rate. When rounded up, whole
+/// buildings are recounted from the summed raw counts, since one building can
+/// feed several targets.
+pub fn merge_summaries(summaries: &[ChainSummary]) -> ChainSummary {
+    let mut building_counts: HashMap<String, f64> = HashMap::new();
+    let mut raw_inputs: HashMap<String, f64> = HashMap::new();
+
+    for summary in summaries {
+        for (name, raw, _) in &summary.building_counts {
+            *building_counts.entry(name.clone()).or_default() += raw;
+        }
+        for (name, rate) in &summary.raw_inputs {
+            *raw_inputs.entry(name.clone()).or_default() += rate;
         }
     }
+
+    let rounded_up = summaries.iter().any(|s| s.rounded_up);
+    let mut building_list: Vec<_> = building_counts
+        .into_iter()
+        .map(|(name, raw)| (name, raw, if rounded_up { raw.ceil() } else { raw }))
+        .collect();
+    building_list.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut raw_list: Vec<_> = raw_inputs.into_iter().collect();
+    raw_list.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let consumption: f64 = summaries.iter().map(|s| s.total_power_consumption).sum();
+    let generation: f64 = summaries.iter().map(|s| s.total_power_generation).sum();
+
+    ChainSummary {
+        target_resource: summaries
+            .iter()
+            .map(|s| s.target_resource.as_str())
+            .collect::<Vec<_>>()
+            .join(" + "),
+        target_rate: summaries.iter().map(|s| s.target_rate).sum(),
+        targets: summaries
+            .iter()
+            .flat_map(|s| s.targets.iter().cloned())
+            .collect(),
+        total_power_consumption: consumption,
+        total_power_generation: generation,
+        net_power: generation - consumption,
+        total_heat_output_dtu: summaries.iter().map(|s| s.total_heat_output_dtu).sum(),
+        building_counts: building_list,
+        rounded_up,
+        raw_inputs: raw_list,
+        byproduct_credits: summaries
+            .iter()
+            .flat_map(|s| s.byproduct_credits.iter().cloned())
+            .collect(),
+    }
 }
 
 impl std::fmt::Display for ChainSummary {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "=== Production Summary ===")?;
-        writeln!(f, "Target: {} @ {:.3} kg/s", self.target_resource, self.target_rate)?;
+        if self.targets.len() > 1 {
+            writeln!(f, "Targets:")?;
+            for (resource, rate) in &self.targets {
+                writeln!(f, "  {} @ {:.3} kg/s", resource, rate)?;
+            }
+        } else {
+            writeln!(
+                f,
+                "Target: {} @ {:.3} kg/s",
+                self.target_resource, self.target_rate
+            )?;
+        }
         writeln!(f)?;
 
         writeln!(f, "Buildings required:")?;
-        for (name, count) in &self.building_counts {
-            writeln!(f, "  {:.2}x {}", count, name)?;
+        for (name, raw, count) in &self.building_counts {
+            if self.rounded_up {
+                writeln!(f, "  {:.2}x {} ({:.2} needed)", count, name, raw)?;
+            } else {
+                writeln!(f, "  {:.2}x {}", count, name)?;
+            }
         }
         writeln!(f)?;
 
@@ -231,11 +1400,89 @@ impl std::fmt::Display for ChainSummary {
         }
         writeln!(f)?;
 
+        if !self.byproduct_credits.is_empty() {
+            writeln!(f, "Covered by byproducts:")?;
+            for (name, rate) in &self.byproduct_credits {
+                writeln!(f, "  {} @ {:.3} kg/s", name, rate)?;
+            }
+            writeln!(f)?;
+        }
+
         writeln!(f, "Power:")?;
         writeln!(f, "  Consumption: {:.0}W", self.total_power_consumption)?;
         writeln!(f, "  Generation:  {:.0}W", self.total_power_generation)?;
         writeln!(f, "  Net:         {:.0}W", self.net_power)?;
+        writeln!(f)?;
+
+        writeln!(f, "Heat:")?;
+        writeln!(
+            f,
+            "  Output: {:.0} DTU/s ({:.3} kDTU/s)",
+            self.total_heat_output_dtu,
+            self.total_heat_output_dtu / 1000.0
+        )?;
+
+        Ok(())
+    }
+}
 
+impl std::fmt::Display for CycleAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps: Vec<_> = self
+            .steps
+            .iter()
+            .map(|(name, count)| format!("{:.2}x {}", count, name))
+            .collect();
+        writeln!(
+            f,
+            "{} -> {} -> {}",
+            self.resource,
+            steps.join(" -> "),
+            self.resource
+        )?;
+        writeln!(
+            f,
+            "  Net {}: {:+.3} kg/s per 1 kg/s fed in",
+            self.resource, self.net_gain_kg_s
+        )?;
+        writeln!(f, "  Power: {:+.0}W", self.power_delta_w)?;
+        for (name, rate) in &self.external_inputs {
+            writeln!(f, "  Also needs {} @ {:.3} kg/s", name, rate)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference_vectors() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv1a::default();
+            hasher.write(bytes);
+            hasher.0
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn optimize_with_no_budget_returns_the_default_chain() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::init_schema(&conn).unwrap();
+        crate::load_sample_data(&conn).unwrap();
+
+        let options = CalcOptions::default();
+        let (chain, score) =
+            optimize(&conn, "Oxygen", 1.0, OptimizeObjective::Power, &options, 0).unwrap();
+        let default = calculate_production_chain(&conn, "Oxygen", 1.0, &options).unwrap();
+        assert_eq!(chain.building_id, default.building_id);
+        assert_eq!(
+            score,
+            objective_score(&conn, &default, OptimizeObjective::Power).unwrap()
+        );
+    }
+}
@@ -1,9 +1,18 @@
 //! Database schema and operations
 
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
 use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
 
-use crate::models::{Building, BuildingInput, BuildingOutput};
+use crate::models::{
+    Building, BuildingComparison, BuildingFilter, BuildingInput, BuildingOutput,
+    BuildingOutputType, BuildingSummary, BuildingType, EfficiencyStats, FieldDifference,
+    FlowAnalysis, FullBuilding, MergeConflict, MergeConflictMode, MergeReport, ProducerOrder,
+    RateOverride, Recipe, RecipeInput, RecipeOutput, RecipeWithIo, Resource,
+};
 
 /// Initialize the database schema
 pub fn init_schema(conn: &Connection) -> Result<()> {
@@ -27,7 +36,16 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             category TEXT,
             power_watts REAL,
             heat_output_dtu REAL,
-            construction_time_s REAL
+            construction_time_s REAL,
+            dlc TEXT,
+            building_type TEXT NOT NULL DEFAULT 'Production',
+            min_critters INTEGER,
+            extractor INTEGER NOT NULL DEFAULT 0,
+            dupe_operated INTEGER NOT NULL DEFAULT 0,
+            passive_thermal INTEGER NOT NULL DEFAULT 0,
+            thermal_mass REAL,
+            heat_conductivity REAL,
+            updated_at TEXT -- UTC 'YYYY-MM-DD HH:MM:SS' of the last write, NULL if written before tracking
         );
 
         -- Building material requirements
@@ -51,14 +69,17 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             building_id TEXT,
             resource_id TEXT,
-            rate_kg_per_s REAL NOT NULL
+            rate_kg_per_s REAL NOT NULL,
+            output_type TEXT NOT NULL DEFAULT 'mass'
         );
 
         -- Some buildings have multiple operational modes (e.g., Metal Refinery recipes)
         CREATE TABLE IF NOT EXISTS recipes (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             building_id TEXT,
-            name TEXT NOT NULL
+            name TEXT NOT NULL,
+            cycle_time_s REAL,
+            recipe_type TEXT
         );
 
         CREATE TABLE IF NOT EXISTS recipe_inputs (
@@ -72,23 +93,175 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             recipe_id INTEGER,
             resource_id TEXT,
             rate_kg_per_s REAL NOT NULL,
+            calories_kcal REAL,
             PRIMARY KEY (recipe_id, resource_id)
         );
 
+        -- Tooltip descriptions from the game's STRINGS, or entered manually
+        CREATE TABLE IF NOT EXISTS building_descriptions (
+            building_id TEXT PRIMARY KEY,
+            description TEXT NOT NULL
+        );
+
+        -- Calories per unit for edible resources
+        CREATE TABLE IF NOT EXISTS food_values (
+            resource_id TEXT PRIMARY KEY,
+            calories_per_unit REAL
+        );
+
+        -- User rate adjustments, read by the calculator before building_inputs/outputs
+        CREATE TABLE IF NOT EXISTS rate_overrides (
+            building_id TEXT,
+            resource_id TEXT,
+            direction TEXT CHECK (direction IN ('input', 'output')),
+            rate_kg_per_s REAL NOT NULL,
+            PRIMARY KEY (building_id, resource_id, direction)
+        );
+
+        -- Extracted rates replaced by apply_efficiency_table, kept so they can be restored
+        CREATE TABLE IF NOT EXISTS rate_originals (
+            building_id TEXT,
+            resource_id TEXT,
+            direction TEXT CHECK (direction IN ('input', 'output')),
+            rate_kg_per_s REAL NOT NULL,
+            PRIMARY KEY (building_id, resource_id, direction)
+        );
+
+        -- Named building views created with create-view, so they can be recreated
+        CREATE TABLE IF NOT EXISTS user_views (
+            name TEXT PRIMARY KEY,
+            definition TEXT NOT NULL
+        );
+
+        -- Key/value facts about the database itself (game version, ...)
+        CREATE TABLE IF NOT EXISTS metadata (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
         -- Create indexes for common lookups
         CREATE INDEX IF NOT EXISTS idx_building_inputs_building ON building_inputs(building_id);
         CREATE INDEX IF NOT EXISTS idx_building_outputs_building ON building_outputs(building_id);
         CREATE INDEX IF NOT EXISTS idx_building_outputs_resource ON building_outputs(resource_id);
         "#,
     )?;
+
+    // Columns added after the original schema
+    add_column_if_missing(conn, "buildings", "dlc", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "buildings",
+        "building_type",
+        "TEXT NOT NULL DEFAULT 'Production'",
+    )?;
+    add_column_if_missing(conn, "buildings", "min_critters", "INTEGER")?;
+    add_column_if_missing(conn, "buildings", "extractor", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(
+        conn,
+        "buildings",
+        "dupe_operated",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        conn,
+        "buildings",
+        "passive_thermal",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(conn, "buildings", "thermal_mass", "REAL")?;
+    add_column_if_missing(conn, "buildings", "heat_conductivity", "REAL")?;
+    add_column_if_missing(conn, "buildings", "updated_at", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "building_outputs",
+        "output_type",
+        "TEXT NOT NULL DEFAULT 'mass'",
+    )?;
+    add_column_if_missing(conn, "recipes", "cycle_time_s", "REAL")?;
+    add_column_if_missing(conn, "recipes", "recipe_type", "TEXT")?;
+    add_column_if_missing(conn, "recipe_outputs", "calories_kcal", "REAL")?;
+
+    create_view_production_summary(conn)?;
+
+    Ok(())
+}
+
+/// Create the convenience views used by listings (no-op if they already exist)
+pub fn create_view_production_summary(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- Per-building I/O counts and total rates
+        CREATE VIEW IF NOT EXISTS v_building_io_summary AS
+        SELECT b.id,
+               b.name,
+               (SELECT COUNT(*) FROM building_inputs bi WHERE bi.building_id = b.id) AS input_count,
+               (SELECT COUNT(*) FROM building_outputs bo WHERE bo.building_id = b.id) AS output_count,
+               (SELECT COALESCE(SUM(rate_kg_per_s), 0) FROM building_inputs bi WHERE bi.building_id = b.id) AS total_input_rate,
+               (SELECT COALESCE(SUM(rate_kg_per_s), 0) FROM building_outputs bo WHERE bo.building_id = b.id) AS total_output_rate
+        FROM buildings b;
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Drop the convenience views so they can be recreated after a schema change
+pub fn drop_views(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP VIEW IF EXISTS v_building_io_summary;")?;
+    Ok(())
+}
+
+/// Add a column to an existing table so older databases pick up schema additions
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, decl
+        ))?;
+    }
     Ok(())
 }
 
+/// Columns selected for a `Building`, in the order `building_from_row` expects
+const BUILDING_COLUMNS: &str = "id, name, category, power_watts, heat_output_dtu, construction_time_s, dlc, building_type, min_critters, extractor, \
+     dupe_operated, passive_thermal, thermal_mass, heat_conductivity";
+
+/// Map the leading `BUILDING_COLUMNS` of a row to a `Building`
+fn building_from_row(row: &Row) -> rusqlite::Result<Building> {
+    Ok(Building {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        category: row.get(2)?,
+        power_watts: row.get(3)?,
+        heat_output_dtu: row.get(4)?,
+        construction_time_s: row.get(5)?,
+        dlc: row.get(6)?,
+        building_type: BuildingType::from_name(&row.get::<_, String>(7)?),
+        min_critters: row.get(8)?,
+        extractor: row.get(9)?,
+        dupe_operated: row.get(10)?,
+        passive_thermal: row.get(11)?,
+        thermal_mass: row.get(12)?,
+        heat_conductivity: row.get(13)?,
+    })
+}
+
 /// Insert or replace a building
+///
+/// An existing building with the same ID has every column overwritten; its
+/// inputs, outputs and recipes are separate rows and are left as they are.
 pub fn upsert_building(conn: &Connection, building: &Building) -> Result<()> {
+    if building.is_raw_resource_sentinel() {
+        anyhow::bail!("building ID '{}' is reserved for raw inputs", building.id);
+    }
     conn.execute(
-        "INSERT OR REPLACE INTO buildings (id, name, category, power_watts, heat_output_dtu, construction_time_s)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT OR REPLACE INTO buildings (id, name, category, power_watts, heat_output_dtu, construction_time_s, dlc, building_type, min_critters, extractor, dupe_operated, passive_thermal, thermal_mass, heat_conductivity, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, datetime('now'))",
         (
             &building.id,
             &building.name,
@@ -96,11 +269,294 @@ pub fn upsert_building(conn: &Connection, building: &Building) -> Result<()> {
             building.power_watts,
             building.heat_output_dtu,
             building.construction_time_s,
+            &building.dlc,
+            building.building_type.as_str(),
+            building.min_critters,
+            building.extractor,
+            building.dupe_operated,
+            building.passive_thermal,
+            building.thermal_mass,
+            building.heat_conductivity,
         ),
     )?;
     Ok(())
 }
 
+/// Insert or replace many buildings with one prepared statement
+///
+/// Runs in its own transaction unless the caller already opened one.
+pub fn upsert_buildings_bulk(conn: &Connection, buildings: &[Building]) -> Result<()> {
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    {
+        let mut stmt = conn.prepare(
+            "INSERT OR REPLACE INTO buildings (id, name, category, power_watts, heat_output_dtu, construction_time_s, dlc, building_type, min_critters, extractor, dupe_operated, passive_thermal, thermal_mass, heat_conductivity, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, datetime('now'))",
+        )?;
+        for building in buildings {
+            if building.is_raw_resource_sentinel() {
+                anyhow::bail!("building ID '{}' is reserved for raw inputs", building.id);
+            }
+            stmt.execute((
+                &building.id,
+                &building.name,
+                &building.category,
+                building.power_watts,
+                building.heat_output_dtu,
+                building.construction_time_s,
+                &building.dlc,
+                building.building_type.as_str(),
+                building.min_critters,
+                building.extractor,
+                building.dupe_operated,
+                building.passive_thermal,
+                building.thermal_mass,
+                building.heat_conductivity,
+            ))?;
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Insert or replace a resource and its thermal properties
+pub fn upsert_resource(conn: &Connection, resource: &Resource) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO resources (id, name, state, specific_heat_capacity, thermal_conductivity, melt_point_c, boil_point_c)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (
+            &resource.id,
+            &resource.name,
+            &resource.state,
+            resource.specific_heat_capacity,
+            resource.thermal_conductivity,
+            resource.melt_point_c,
+            resource.boil_point_c,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Insert or replace a building recipe and its inputs/outputs, returning the recipe ID
+///
+/// Recipes are identified by `(building_id, name)`; an existing recipe has its
+/// inputs and outputs replaced. `recipe_type` is e.g. "Food" for cooking recipes.
+pub fn upsert_recipe(
+    conn: &Connection,
+    building_id: &str,
+    name: &str,
+    inputs: &[(String, f64)],
+    outputs: &[(String, f64)],
+    cycle_time_s: Option<f64>,
+    recipe_type: Option<&str>,
+) -> Result<i64> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM recipes WHERE building_id = ?1 AND name = ?2",
+            (building_id, name),
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let recipe_id = match existing {
+        Some(id) => {
+            conn.execute("DELETE FROM recipe_inputs WHERE recipe_id = ?1", [id])?;
+            conn.execute("DELETE FROM recipe_outputs WHERE recipe_id = ?1", [id])?;
+            conn.execute(
+                "UPDATE recipes SET cycle_time_s = ?2, recipe_type = ?3 WHERE id = ?1",
+                (id, cycle_time_s, recipe_type),
+            )?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO recipes (building_id, name, cycle_time_s, recipe_type) VALUES (?1, ?2, ?3, ?4)",
+                (building_id, name, cycle_time_s, recipe_type),
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+
+    // Duplicate resources within a recipe are summed rather than violating the primary key
+    for (resource_id, rate) in inputs {
+        conn.execute(
+            "INSERT INTO recipe_inputs (recipe_id, resource_id, rate_kg_per_s) VALUES (?1, ?2, ?3)
+             ON CONFLICT (recipe_id, resource_id) DO UPDATE SET rate_kg_per_s = rate_kg_per_s + excluded.rate_kg_per_s",
+            (recipe_id, resource_id, rate),
+        )?;
+    }
+    for (resource_id, rate) in outputs {
+        conn.execute(
+            "INSERT INTO recipe_outputs (recipe_id, resource_id, rate_kg_per_s) VALUES (?1, ?2, ?3)
+             ON CONFLICT (recipe_id, resource_id) DO UPDATE SET rate_kg_per_s = rate_kg_per_s + excluded.rate_kg_per_s",
+            (recipe_id, resource_id, rate),
+        )?;
+    }
+
+    Ok(recipe_id)
+}
+
+/// Insert or replace the calories per unit of an edible resource
+pub fn upsert_food_value(
+    conn: &Connection,
+    resource_id: &str,
+    calories_per_unit: Option<f64>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO food_values (resource_id, calories_per_unit) VALUES (?1, ?2)",
+        (resource_id, calories_per_unit),
+    )?;
+    Ok(())
+}
+
+/// List food resources with their calories per unit (kcal), if known
+///
+/// Includes everything in `food_values` plus anything cooked by a "Food" building.
+pub fn list_foods(conn: &Connection) -> Result<Vec<(String, Option<f64>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.id, fv.calories_per_unit
+         FROM (
+             SELECT resource_id AS id FROM food_values
+             UNION
+             SELECT ro.resource_id FROM recipe_outputs ro
+             JOIN recipes r ON r.id = ro.recipe_id
+             JOIN buildings b ON b.id = r.building_id
+             WHERE b.category = 'Food'
+         ) f
+         LEFT JOIN food_values fv ON fv.resource_id = f.id
+         ORDER BY f.id",
+    )?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// List resource IDs whose stored state (Solid, Liquid, Gas) matches
+pub fn list_resource_ids_in_state(conn: &Connection, state: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM resources WHERE state = ?1 ORDER BY id")?;
+
+    let rows = stmt.query_map([state], |row| row.get(0))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Update the display name of an existing building
+pub fn update_building_name(conn: &Connection, id: &str, new_name: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE buildings SET name = ?2, updated_at = datetime('now') WHERE id = ?1",
+        (id, new_name),
+    )?;
+    Ok(())
+}
+
+/// Change a building's ID everywhere it is referenced, e.g. after a game update renames it
+///
+/// Fails if `old_id` doesn't exist or `new_id` is already taken. Runs in its
+/// own transaction unless the caller already opened one.
+pub fn rename_building(conn: &Connection, old_id: &str, new_id: &str) -> Result<()> {
+    if get_building(conn, old_id)?.is_none() {
+        anyhow::bail!("building '{}' not found", old_id);
+    }
+    if get_building(conn, new_id)?.is_some() {
+        anyhow::bail!("building '{}' already exists", new_id);
+    }
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    for table in [
+        "building_inputs",
+        "building_outputs",
+        "building_materials",
+        "recipes",
+        "building_descriptions",
+        "rate_overrides",
+        "rate_originals",
+    ] {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET building_id = ?2 WHERE building_id = ?1",
+                table
+            ),
+            (old_id, new_id),
+        )?;
+    }
+    conn.execute(
+        "UPDATE buildings SET id = ?2, updated_at = datetime('now') WHERE id = ?1",
+        (old_id, new_id),
+    )?;
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Columns read by `resource_from_row`, in order
+const RESOURCE_COLUMNS: &str =
+    "id, name, state, specific_heat_capacity, thermal_conductivity, melt_point_c, boil_point_c";
+
+/// Map a row selected with `RESOURCE_COLUMNS` to a `Resource`
+fn resource_from_row(row: &Row) -> rusqlite::Result<Resource> {
+    Ok(Resource {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        state: row.get(2)?,
+        specific_heat_capacity: row.get(3)?,
+        thermal_conductivity: row.get(4)?,
+        melt_point_c: row.get(5)?,
+        boil_point_c: row.get(6)?,
+    })
+}
+
+/// Get a resource by ID
+pub fn get_resource(conn: &Connection, id: &str) -> Result<Option<Resource>> {
+    let resource = conn
+        .query_row(
+            &format!("SELECT {} FROM resources WHERE id = ?1", RESOURCE_COLUMNS),
+            [id],
+            resource_from_row,
+        )
+        .optional()?;
+    Ok(resource)
+}
+
+/// List all resources with element properties, ordered by ID
+pub fn list_resources(conn: &Connection) -> Result<Vec<Resource>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM resources ORDER BY id",
+        RESOURCE_COLUMNS
+    ))?;
+    let resources = stmt
+        .query_map([], resource_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(resources)
+}
+
+/// Get a single building by ID
+pub fn get_building(conn: &Connection, id: &str) -> Result<Option<Building>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM buildings WHERE id = ?1",
+        BUILDING_COLUMNS
+    ))?;
+
+    let building = stmt.query_row([id], building_from_row).optional()?;
+
+    Ok(building)
+}
+
 /// Insert a building input
 pub fn insert_building_input(conn: &Connection, input: &BuildingInput) -> Result<()> {
     conn.execute(
@@ -111,16 +567,119 @@ pub fn insert_building_input(conn: &Connection, input: &BuildingInput) -> Result
     Ok(())
 }
 
+/// Insert many building inputs with one prepared statement
+///
+/// Runs in its own transaction unless the caller already opened one.
+pub fn insert_building_inputs_bulk(conn: &Connection, inputs: &[BuildingInput]) -> Result<()> {
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    {
+        let mut stmt = conn.prepare(
+            "INSERT INTO building_inputs (building_id, resource_id, rate_kg_per_s)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for input in inputs {
+            stmt.execute((&input.building_id, &input.resource_id, input.rate_kg_per_s))?;
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Insert many building outputs with one prepared statement
+///
+/// Runs in its own transaction unless the caller already opened one.
+pub fn insert_building_outputs_bulk(conn: &Connection, outputs: &[BuildingOutput]) -> Result<()> {
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    {
+        let mut stmt = conn.prepare(
+            "INSERT INTO building_outputs (building_id, resource_id, rate_kg_per_s, output_type)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for output in outputs {
+            stmt.execute((
+                &output.building_id,
+                &output.resource_id,
+                output.rate_kg_per_s,
+                output.output_type.as_str(),
+            ))?;
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
 /// Insert a building output
 pub fn insert_building_output(conn: &Connection, output: &BuildingOutput) -> Result<()> {
     conn.execute(
-        "INSERT INTO building_outputs (building_id, resource_id, rate_kg_per_s)
-         VALUES (?1, ?2, ?3)",
-        (&output.building_id, &output.resource_id, output.rate_kg_per_s),
+        "INSERT INTO building_outputs (building_id, resource_id, rate_kg_per_s, output_type)
+         VALUES (?1, ?2, ?3, ?4)",
+        (
+            &output.building_id,
+            &output.resource_id,
+            output.rate_kg_per_s,
+            output.output_type.as_str(),
+        ),
+    )?;
+    Ok(())
+}
+
+/// Insert or replace the tooltip description for a building
+pub fn upsert_building_description(
+    conn: &Connection,
+    building_id: &str,
+    description: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO building_descriptions (building_id, description) VALUES (?1, ?2)",
+        (building_id, description),
+    )?;
+    Ok(())
+}
+
+/// Get the tooltip description for a building, if one is stored
+pub fn get_building_description(conn: &Connection, building_id: &str) -> Result<Option<String>> {
+    let description = conn
+        .query_row(
+            "SELECT description FROM building_descriptions WHERE building_id = ?1",
+            [building_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(description)
+}
+
+/// `metadata` key for the game version the data was extracted from
+pub const METADATA_GAME_VERSION: &str = "game_version";
+
+/// Insert or replace a database metadata value
+pub fn set_metadata(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+        (key, value),
     )?;
     Ok(())
 }
 
+/// Get a database metadata value, if set
+pub fn get_metadata(conn: &Connection, key: &str) -> Result<Option<String>> {
+    let value = conn
+        .query_row("SELECT value FROM metadata WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    Ok(value)
+}
+
 /// Clear all extracted data (for re-extraction)
 pub fn clear_extracted_data(conn: &Connection) -> Result<()> {
     conn.execute_batch(
@@ -131,34 +690,477 @@ pub fn clear_extracted_data(conn: &Connection) -> Result<()> {
         DELETE FROM building_outputs;
         DELETE FROM building_inputs;
         DELETE FROM building_materials;
+        DELETE FROM building_descriptions;
+        DELETE FROM food_values;
         DELETE FROM buildings;
         DELETE FROM resources;
+        DELETE FROM metadata;
+        DELETE FROM rate_originals;
         "#,
     )?;
     Ok(())
 }
 
+/// Get a building together with its description, I/O, materials and recipes
+pub fn get_full_building(conn: &Connection, id: &str) -> Result<Option<FullBuilding>> {
+    let Some(building) = get_building(conn, id)? else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT resource_id, mass_kg FROM building_materials WHERE building_id = ?1 ORDER BY resource_id",
+    )?;
+    let materials = stmt
+        .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Some(FullBuilding {
+        description: get_building_description(conn, id)?,
+        inputs: get_building_inputs(conn, id)?,
+        outputs: get_building_outputs(conn, id)?,
+        materials,
+        recipes: get_recipes_for_building(conn, id)?,
+        building,
+    }))
+}
+
+/// Columns read by `recipe_from_row`, in order
+const RECIPE_COLUMNS: &str = "id, building_id, name, cycle_time_s, recipe_type";
+
+/// Map a row selected with `RECIPE_COLUMNS` to a `Recipe`
+fn recipe_from_row(row: &Row) -> rusqlite::Result<Recipe> {
+    Ok(Recipe {
+        id: row.get(0)?,
+        building_id: row.get(1)?,
+        name: row.get(2)?,
+        cycle_time_s: row.get(3)?,
+        recipe_type: row.get(4)?,
+    })
+}
+
+/// Attach each recipe's inputs and outputs
+fn with_recipe_io(conn: &Connection, recipe_rows: Vec<Recipe>) -> Result<Vec<RecipeWithIo>> {
+    let mut recipes = Vec::new();
+    for recipe in recipe_rows {
+        let inputs = get_recipe_inputs(conn, recipe.id)?;
+        let outputs = get_recipe_outputs(conn, recipe.id)?;
+        recipes.push((recipe, inputs, outputs));
+    }
+    Ok(recipes)
+}
+
+/// Get a building's recipes, ordered by name, each with its inputs and outputs
+///
+/// Recipes without inputs or outputs are returned with empty lists.
+pub fn get_recipes_for_building(conn: &Connection, building_id: &str) -> Result<Vec<RecipeWithIo>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM recipes WHERE building_id = ?1 ORDER BY name",
+        RECIPE_COLUMNS
+    ))?;
+    let recipe_rows = stmt
+        .query_map([building_id], recipe_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    with_recipe_io(conn, recipe_rows)
+}
+
+/// Get every recipe tagged "Food", with its inputs and outputs
+pub fn list_food_recipes(conn: &Connection) -> Result<Vec<RecipeWithIo>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM recipes WHERE recipe_type = 'Food' ORDER BY building_id, name",
+        RECIPE_COLUMNS
+    ))?;
+    let recipe_rows = stmt
+        .query_map([], recipe_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    with_recipe_io(conn, recipe_rows)
+}
+
+/// Copy calories from `food_values` onto the outputs of "Food" recipes, returning the rows updated
+///
+/// Food rates are in units, and one unit of food weighs 1 kg, so kcal/unit is also kcal/kg.
+pub fn update_recipe_calories(conn: &Connection) -> Result<usize> {
+    let updated = conn.execute(
+        "UPDATE recipe_outputs
+         SET calories_kcal = (SELECT calories_per_unit FROM food_values WHERE resource_id = recipe_outputs.resource_id)
+         WHERE recipe_id IN (SELECT id FROM recipes WHERE recipe_type = 'Food')
+           AND resource_id IN (SELECT resource_id FROM food_values)",
+        [],
+    )?;
+    Ok(updated)
+}
+
+/// Get the inputs of one recipe
+pub fn get_recipe_inputs(conn: &Connection, recipe_id: i64) -> Result<Vec<RecipeInput>> {
+    let mut stmt = conn.prepare(
+        "SELECT recipe_id, resource_id, rate_kg_per_s FROM recipe_inputs WHERE recipe_id = ?1",
+    )?;
+    let inputs = stmt
+        .query_map([recipe_id], |row| {
+            Ok(RecipeInput {
+                recipe_id: row.get(0)?,
+                resource_id: row.get(1)?,
+                rate_kg_per_s: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(inputs)
+}
+
+/// Get the outputs of one recipe
+pub fn get_recipe_outputs(conn: &Connection, recipe_id: i64) -> Result<Vec<RecipeOutput>> {
+    let mut stmt = conn.prepare(
+        "SELECT recipe_id, resource_id, rate_kg_per_s, calories_kcal FROM recipe_outputs WHERE recipe_id = ?1",
+    )?;
+    let outputs = stmt
+        .query_map([recipe_id], |row| {
+            Ok(RecipeOutput {
+                recipe_id: row.get(0)?,
+                resource_id: row.get(1)?,
+                rate_kg_per_s: row.get(2)?,
+                calories_kcal: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(outputs)
+}
+
+/// Get the recipes named `name` (case-insensitive), across all buildings
+pub fn get_recipes_by_name(conn: &Connection, name: &str) -> Result<Vec<Recipe>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM recipes WHERE name = ?1 COLLATE NOCASE ORDER BY building_id",
+        RECIPE_COLUMNS
+    ))?;
+    let recipes = stmt
+        .query_map([name], recipe_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(recipes)
+}
+
+/// Compare two buildings field by field, including each input and output rate
+///
+/// Returns `None` if either building doesn't exist.
+pub fn compare_buildings(
+    conn: &Connection,
+    id_a: &str,
+    id_b: &str,
+) -> Result<Option<BuildingComparison>> {
+    let (Some(a), Some(b)) = (
+        get_full_building(conn, id_a)?,
+        get_full_building(conn, id_b)?,
+    ) else {
+        return Ok(None);
+    };
+
+    let fields_a = comparison_fields(&a);
+    let fields_b = comparison_fields(&b);
+    let lookup = |fields: &[(String, String)], name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    // Fields of `a` first, then those only `b` has
+    let mut names: Vec<&String> = Vec::new();
+    for (field, _) in fields_a.iter().chain(&fields_b) {
+        if !names.contains(&field) {
+            names.push(field);
+        }
+    }
+
+    let mut differences = Vec::new();
+    for name in names {
+        let (value_a, value_b) = (lookup(&fields_a, name), lookup(&fields_b, name));
+        if value_a != value_b {
+            differences.push(FieldDifference {
+                field: name.clone(),
+                a: value_a,
+                b: value_b,
+            });
+        }
+    }
+
+    Ok(Some(BuildingComparison {
+        id_a: id_a.to_string(),
+        id_b: id_b.to_string(),
+        differences,
+    }))
+}
+
+/// Copy every building of `source` into `dest` with its inputs, outputs, materials, recipes and description
+///
+/// Buildings identical in both (as compared by `compare_buildings`) are left
+/// alone; `mode` decides what happens to those that differ. Runs in its own
+/// transaction unless the caller already opened one.
+pub fn merge_database(
+    dest: &Connection,
+    source: &Connection,
+    mode: MergeConflictMode,
+) -> Result<MergeReport> {
+    let tx = dest
+        .is_autocommit()
+        .then(|| dest.unchecked_transaction())
+        .transpose()?;
+    let mut report = MergeReport::default();
+    for building in list_buildings(source)? {
+        let Some(incoming) = get_full_building(source, &building.id)? else {
+            continue;
+        };
+        if let Some(existing) = get_full_building(dest, &building.id)? {
+            let mut ours = comparison_fields(&existing);
+            let mut theirs = comparison_fields(&incoming);
+            ours.sort();
+            theirs.sort();
+            if ours == theirs {
+                continue;
+            }
+            match mode {
+                MergeConflictMode::Skip => continue,
+                MergeConflictMode::Overwrite => {}
+                MergeConflictMode::Report => {
+                    report.conflicts.push(MergeConflict {
+                        building_id: building.id,
+                        source: incoming,
+                        destination: existing,
+                    });
+                    continue;
+                }
+            }
+        }
+        replace_full_building(dest, &incoming)?;
+        report.merged_count += 1;
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(report)
+}
+
+/// Write `full` over any existing building with the same ID, replacing all of its rows
+fn replace_full_building(conn: &Connection, full: &FullBuilding) -> Result<()> {
+    let id = &full.building.id;
+    for table in ["recipe_inputs", "recipe_outputs"] {
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE recipe_id IN (SELECT id FROM recipes WHERE building_id = ?1)",
+                table
+            ),
+            [id],
+        )?;
+    }
+    for table in [
+        "recipes",
+        "building_inputs",
+        "building_outputs",
+        "building_materials",
+        "building_descriptions",
+    ] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE building_id = ?1", table),
+            [id],
+        )?;
+    }
+
+    upsert_building(conn, &full.building)?;
+    insert_building_inputs_bulk(conn, &full.inputs)?;
+    insert_building_outputs_bulk(conn, &full.outputs)?;
+    for (resource_id, mass_kg) in &full.materials {
+        conn.execute(
+            "INSERT INTO building_materials (building_id, resource_id, mass_kg) VALUES (?1, ?2, ?3)",
+            (id, resource_id, mass_kg),
+        )?;
+    }
+    if let Some(description) = &full.description {
+        upsert_building_description(conn, id, description)?;
+    }
+    for (recipe, inputs, full_outputs) in &full.recipes {
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|i| (i.resource_id.clone(), i.rate_kg_per_s))
+            .collect();
+        let outputs: Vec<_> = full_outputs
+            .iter()
+            .map(|o| (o.resource_id.clone(), o.rate_kg_per_s))
+            .collect();
+        let recipe_type = recipe.recipe_type.as_deref();
+        let recipe_id = upsert_recipe(
+            conn,
+            id,
+            &recipe.name,
+            &inputs,
+            &outputs,
+            recipe.cycle_time_s,
+            recipe_type,
+        )?;
+        for output in full_outputs.iter().filter(|o| o.calories_kcal.is_some()) {
+            conn.execute(
+                "UPDATE recipe_outputs SET calories_kcal = ?3 WHERE recipe_id = ?1 AND resource_id = ?2",
+                (recipe_id, &output.resource_id, output.calories_kcal),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `(field, value)` pairs compared by `compare_buildings` and `merge_database`
+fn comparison_fields(full: &FullBuilding) -> Vec<(String, String)> {
+    let building = &full.building;
+    let mut fields = vec![
+        ("name".to_string(), building.name.clone()),
+        (
+            "type".to_string(),
+            building.building_type.as_str().to_string(),
+        ),
+        ("power (W)".to_string(), format!("{}", building.power_watts)),
+        (
+            "heat (DTU/s)".to_string(),
+            format!("{}", building.heat_output_dtu),
+        ),
+    ];
+    if let Some(category) = &building.category {
+        fields.push(("category".to_string(), category.clone()));
+    }
+    if let Some(dlc) = &building.dlc {
+        fields.push(("dlc".to_string(), dlc.clone()));
+    }
+    if let Some(critters) = building.min_critters {
+        fields.push(("critters".to_string(), critters.to_string()));
+    }
+    if building.extractor {
+        fields.push(("extractor".to_string(), "yes".to_string()));
+    }
+    if building.dupe_operated {
+        fields.push(("dupe operated".to_string(), "yes".to_string()));
+    }
+    if building.passive_thermal {
+        fields.push(("passive thermal".to_string(), "yes".to_string()));
+    }
+    if let Some(mass) = building.thermal_mass {
+        fields.push(("thermal mass".to_string(), format!("{}", mass)));
+    }
+    if let Some(conductivity) = building.heat_conductivity {
+        fields.push(("heat conductivity".to_string(), format!("{}", conductivity)));
+    }
+    if let Some(time) = building.construction_time_s {
+        fields.push(("construction (s)".to_string(), format!("{}", time)));
+    }
+    if let Some(description) = &full.description {
+        fields.push(("description".to_string(), description.clone()));
+    }
+    for input in &full.inputs {
+        fields.push((
+            format!("input {}", input.resource_id),
+            format!("{:.4}", input.rate_kg_per_s),
+        ));
+    }
+    for output in &full.outputs {
+        let rate = match output.output_type {
+            BuildingOutputType::Mass => format!("{:.4}", output.rate_kg_per_s),
+            other => format!("{:.4} {}", output.rate_kg_per_s, other.unit()),
+        };
+        fields.push((format!("output {}", output.resource_id), rate));
+    }
+    for (resource_id, mass_kg) in &full.materials {
+        fields.push((
+            format!("material {}", resource_id),
+            format!("{} kg", mass_kg),
+        ));
+    }
+    for (recipe, inputs, outputs) in &full.recipes {
+        let name = &recipe.name;
+        if let Some(time) = recipe.cycle_time_s {
+            fields.push((format!("recipe {} time (s)", name), format!("{}", time)));
+        }
+        if let Some(recipe_type) = &recipe.recipe_type {
+            fields.push((format!("recipe {} type", name), recipe_type.clone()));
+        }
+        for input in inputs {
+            fields.push((
+                format!("recipe {} input {}", name, input.resource_id),
+                format!("{:.4}", input.rate_kg_per_s),
+            ));
+        }
+        for output in outputs {
+            let calories = output
+                .calories_kcal
+                .map(|kcal| format!(", {} kcal/kg", kcal))
+                .unwrap_or_default();
+            fields.push((
+                format!("recipe {} output {}", name, output.resource_id),
+                format!("{:.4}{}", output.rate_kg_per_s, calories),
+            ));
+        }
+    }
+    fields
+}
+
+/// Total construction mass of one building in kg (0 if no materials are stored)
+pub fn get_building_material_mass(conn: &Connection, building_id: &str) -> Result<f64> {
+    let mass = conn.query_row(
+        "SELECT COALESCE(SUM(mass_kg), 0) FROM building_materials WHERE building_id = ?1",
+        [building_id],
+        |row| row.get(0),
+    )?;
+    Ok(mass)
+}
+
 /// Get all buildings that produce a given resource
-pub fn get_producers(conn: &Connection, resource_id: &str) -> Result<Vec<(Building, f64)>> {
+///
+/// Buildings in `prefer_category` (case-insensitive) come first; otherwise
+/// producers keep the order their outputs were inserted in.
+pub fn get_producers(
+    conn: &Connection,
+    resource_id: &str,
+    prefer_category: Option<&str>,
+) -> Result<Vec<(Building, f64)>> {
     let mut stmt = conn.prepare(
-        "SELECT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, bo.rate_kg_per_s
+        "SELECT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, b.dlc, b.building_type, b.min_critters, b.extractor, b.dupe_operated, b.passive_thermal, b.thermal_mass, b.heat_conductivity, bo.rate_kg_per_s
          FROM buildings b
          JOIN building_outputs bo ON b.id = bo.building_id
-         WHERE bo.resource_id = ?1",
+         WHERE bo.resource_id = ?1 AND bo.output_type = 'mass'
+         ORDER BY CASE WHEN b.category = ?2 COLLATE NOCASE THEN 0 ELSE 1 END, bo.id",
     )?;
 
+    let rows = stmt.query_map((resource_id, prefer_category), |row| {
+        Ok((building_from_row(row)?, row.get::<_, f64>(14)?))
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Get all buildings that produce a given resource, sorted by `order`
+///
+/// Ties keep the order the outputs were inserted in.
+pub fn get_producers_ordered_by(
+    conn: &Connection,
+    resource_id: &str,
+    order: ProducerOrder,
+) -> Result<Vec<(Building, f64)>> {
+    let order_by = match order {
+        ProducerOrder::Rate => "bo.rate_kg_per_s DESC",
+        ProducerOrder::PowerEfficiency => {
+            "b.power_watts / NULLIF(bo.rate_kg_per_s, 0) DESC NULLS LAST"
+        }
+        ProducerOrder::HeatOutput => {
+            "b.heat_output_dtu / NULLIF(bo.rate_kg_per_s, 0) ASC NULLS LAST"
+        }
+        ProducerOrder::Alphabetical => "b.name",
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, b.dlc, b.building_type, b.min_critters, b.extractor, b.dupe_operated, b.passive_thermal, b.thermal_mass, b.heat_conductivity, bo.rate_kg_per_s
+         FROM buildings b
+         JOIN building_outputs bo ON b.id = bo.building_id
+         WHERE bo.resource_id = ?1 AND bo.output_type = 'mass'
+         ORDER BY {}, bo.id",
+        order_by
+    ))?;
+
     let rows = stmt.query_map([resource_id], |row| {
-        Ok((
-            Building {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                power_watts: row.get(3)?,
-                heat_output_dtu: row.get(4)?,
-                construction_time_s: row.get(5)?,
-            },
-            row.get::<_, f64>(6)?,
-        ))
+        Ok((building_from_row(row)?, row.get::<_, f64>(14)?))
     })?;
 
     let mut results = Vec::new();
@@ -191,20 +1193,20 @@ pub fn get_building_inputs(conn: &Connection, building_id: &str) -> Result<Vec<B
     Ok(results)
 }
 
-/// List all buildings in the database
-pub fn list_buildings(conn: &Connection) -> Result<Vec<Building>> {
+/// Get all outputs for a building
+pub fn get_building_outputs(conn: &Connection, building_id: &str) -> Result<Vec<BuildingOutput>> {
     let mut stmt = conn.prepare(
-        "SELECT id, name, category, power_watts, heat_output_dtu, construction_time_s FROM buildings ORDER BY name",
+        "SELECT building_id, resource_id, rate_kg_per_s, output_type
+         FROM building_outputs
+         WHERE building_id = ?1",
     )?;
 
-    let rows = stmt.query_map([], |row| {
-        Ok(Building {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            category: row.get(2)?,
-            power_watts: row.get(3)?,
-            heat_output_dtu: row.get(4)?,
-            construction_time_s: row.get(5)?,
+    let rows = stmt.query_map([building_id], |row| {
+        Ok(BuildingOutput {
+            building_id: row.get(0)?,
+            resource_id: row.get(1)?,
+            rate_kg_per_s: row.get(2)?,
+            output_type: BuildingOutputType::from_name(&row.get::<_, String>(3)?),
         })
     })?;
 
@@ -215,13 +1217,14 @@ pub fn list_buildings(conn: &Connection) -> Result<Vec<Building>> {
     Ok(results)
 }
 
-/// List all unique resources that are outputs
-pub fn list_producible_resources(conn: &Connection) -> Result<Vec<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT DISTINCT resource_id FROM building_outputs ORDER BY resource_id",
-    )?;
+/// List all buildings in the database
+pub fn list_buildings(conn: &Connection) -> Result<Vec<Building>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM buildings ORDER BY name",
+        BUILDING_COLUMNS
+    ))?;
 
-    let rows = stmt.query_map([], |row| row.get(0))?;
+    let rows = stmt.query_map([], building_from_row)?;
 
     let mut results = Vec::new();
     for row in rows {
@@ -229,3 +1232,1461 @@ pub fn list_producible_resources(conn: &Connection) -> Result<Vec<String>> {
     }
     Ok(results)
 }
+
+/// Buildings that remove heat (negative heat output), most cooling first
+pub fn get_buildings_with_heat_cooling(conn: &Connection) -> Result<Vec<Building>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM buildings WHERE heat_output_dtu < 0 ORDER BY heat_output_dtu, name",
+        BUILDING_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], building_from_row)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// List all buildings, quickest to build first (unknown construction times last)
+pub fn list_buildings_by_construction_time(conn: &Connection) -> Result<Vec<Building>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM buildings ORDER BY construction_time_s IS NULL, construction_time_s, name",
+        BUILDING_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], building_from_row)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Seconds to construct a chain's buildings: `ceil(count) * construction_time_s` summed over
+/// `(building_id, count)` pairs; buildings without a known construction time add nothing
+pub fn total_construction_time_for_chain(
+    conn: &Connection,
+    building_counts: &[(String, f64)],
+) -> Result<f64> {
+    let mut stmt =
+        conn.prepare("SELECT COALESCE(construction_time_s, 0) FROM buildings WHERE id = ?1")?;
+    let mut total = 0.0;
+    for (building_id, count) in building_counts {
+        let time: Option<f64> = stmt.query_row([building_id], |row| row.get(0)).optional()?;
+        total += count.ceil() * time.unwrap_or(0.0);
+    }
+    Ok(total)
+}
+
+/// Buildings with no inputs, outputs or recipes, i.e. likely extraction failures
+///
+/// Service buildings (skill and morale stations), passive thermal buildings
+/// (insulation, tempshift plates) and heat exchangers (which remove heat from
+/// piped fluids) have no I/O by design and are excluded.
+pub fn list_buildings_missing_io(conn: &Connection) -> Result<Vec<Building>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM buildings b
+         WHERE b.building_type != 'Services'
+           AND NOT b.passive_thermal
+           AND b.heat_output_dtu >= 0
+           AND NOT EXISTS (SELECT 1 FROM building_inputs WHERE building_id = b.id)
+           AND NOT EXISTS (SELECT 1 FROM building_outputs WHERE building_id = b.id)
+           AND NOT EXISTS (SELECT 1 FROM recipes WHERE building_id = b.id)
+         ORDER BY b.name",
+        BUILDING_COLUMNS
+    ))?;
+
+    let rows = stmt.query_map([], building_from_row)?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Input/output row problems: `(issue, table, WHERE clause selecting the offending rows)`
+///
+/// Duplicates are rows identical to an earlier one; the earliest is kept.
+const IO_ISSUES: [(&str, &str, &str); 4] = [
+    ("zero-rate inputs", "building_inputs", "rate_kg_per_s = 0"),
+    ("zero-rate outputs", "building_outputs", "rate_kg_per_s = 0"),
+    (
+        "duplicate inputs",
+        "building_inputs",
+        "id NOT IN (SELECT MIN(id) FROM building_inputs GROUP BY building_id, resource_id, rate_kg_per_s)",
+    ),
+    (
+        "duplicate outputs",
+        "building_outputs",
+        "id NOT IN (SELECT MIN(id) FROM building_outputs GROUP BY building_id, resource_id, rate_kg_per_s, output_type)",
+    ),
+];
+
+/// Count zero-rate and duplicate input/output rows, as `(issue, rows)`
+pub fn count_io_issues(conn: &Connection) -> Result<Vec<(&'static str, usize)>> {
+    let mut counts = Vec::new();
+    for (issue, table, condition) in IO_ISSUES {
+        let count: usize = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition),
+            [],
+            |row| row.get(0),
+        )?;
+        counts.push((issue, count));
+    }
+    Ok(counts)
+}
+
+/// Fix the problems reported by `count_io_issues` and `list_buildings_missing_io`
+///
+/// Deletes zero-rate and duplicate input/output rows, then buildings left with no
+/// inputs, outputs or recipes. Returns a description of each fix applied. Everything
+/// runs in one transaction, which is rolled back (and an error returned) if more than
+/// `max_changes` rows would change.
+pub fn fix_data_quality(conn: &Connection, max_changes: usize) -> Result<Vec<String>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut fixes = Vec::new();
+    let mut changes = 0;
+
+    for (issue, table, condition) in IO_ISSUES {
+        let deleted = tx.execute(&format!("DELETE FROM {} WHERE {}", table, condition), [])?;
+        if deleted > 0 {
+            fixes.push(format!("removed {} {}", deleted, issue));
+            changes += deleted;
+        }
+    }
+
+    for building in list_buildings_missing_io(&tx)? {
+        for table in [
+            "building_materials",
+            "building_descriptions",
+            "rate_overrides",
+            "rate_originals",
+        ] {
+            changes += tx.execute(
+                &format!("DELETE FROM {} WHERE building_id = ?1", table),
+                [&building.id],
+            )?;
+        }
+        changes += tx.execute("DELETE FROM buildings WHERE id = ?1", [&building.id])?;
+        fixes.push(format!(
+            "deleted {} ({}), which has no inputs, outputs or recipes",
+            building.name, building.id
+        ));
+    }
+
+    if changes > max_changes {
+        anyhow::bail!(
+            "fixes would change {} rows, more than the limit of {}; rolled back",
+            changes,
+            max_changes
+        );
+    }
+    tx.commit()?;
+    Ok(fixes)
+}
+
+/// Find other buildings sharing at least one output resource with `building_id`
+///
+/// Sorted by number of shared outputs, most first.
+pub fn find_substitute_buildings(conn: &Connection, building_id: &str) -> Result<Vec<Building>> {
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, b.dlc, b.building_type, b.min_critters, b.extractor, b.dupe_operated, b.passive_thermal, b.thermal_mass, b.heat_conductivity
+         FROM buildings b
+         JOIN building_outputs other ON other.building_id = b.id
+         WHERE b.id != ?1
+           AND other.resource_id IN (SELECT resource_id FROM building_outputs WHERE building_id = ?1)
+         GROUP BY b.id
+         ORDER BY COUNT(DISTINCT other.resource_id) DESC, b.name",
+    )?;
+
+    let rows = stmt.query_map([building_id], building_from_row)?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Find buildings that take `output_resource` of `source_building` as an input
+///
+/// Returns nothing if `source_building` does not output that resource.
+pub fn find_buildings_consuming_byproduct(
+    conn: &Connection,
+    source_building: &str,
+    output_resource: &str,
+) -> Result<Vec<Building>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, b.dlc, b.building_type, b.min_critters, b.extractor, b.dupe_operated, b.passive_thermal, b.thermal_mass, b.heat_conductivity
+         FROM buildings b
+         JOIN building_inputs bi ON bi.building_id = b.id
+         WHERE bi.resource_id = ?2
+           AND b.id != ?1
+           AND EXISTS (SELECT 1 FROM building_outputs WHERE building_id = ?1 AND resource_id = ?2)
+         ORDER BY b.name",
+    )?;
+
+    let rows = stmt.query_map([source_building, output_resource], building_from_row)?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// List buildings with only the columns needed for a listing
+pub fn list_buildings_summary(conn: &Connection) -> Result<Vec<BuildingSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, category, power_watts, dlc, dupe_operated FROM buildings ORDER BY name",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(BuildingSummary {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            category: row.get(2)?,
+            power_watts: row.get(3)?,
+            dlc: row.get(4)?,
+            dupe_operated: row.get(5)?,
+        })
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Count inputs and outputs per building, keyed by building ID
+pub fn count_building_io(conn: &Connection) -> Result<HashMap<String, (usize, usize)>> {
+    let mut stmt =
+        conn.prepare("SELECT id, input_count, output_count FROM v_building_io_summary")?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            (
+                row.get::<_, i64>(1)? as usize,
+                row.get::<_, i64>(2)? as usize,
+            ),
+        ))
+    })?;
+
+    let mut results = HashMap::new();
+    for row in rows {
+        let (id, counts) = row?;
+        results.insert(id, counts);
+    }
+    Ok(results)
+}
+
+/// Find buildings reachable from `resource` by following outputs into inputs
+///
+/// Buildings consuming `resource` directly are at distance 1; those consuming
+/// their outputs at 2, and so on up to `max_steps`. Sorted by distance, then name.
+pub fn get_buildings_reachable_from(
+    conn: &Connection,
+    resource: &str,
+    max_steps: usize,
+) -> Result<Vec<(Building, usize)>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE reach(building_id, step) AS (
+             SELECT building_id, 1 FROM building_inputs WHERE resource_id = ?1
+             UNION
+             SELECT bi.building_id, r.step + 1
+             FROM reach r
+             JOIN building_outputs bo ON bo.building_id = r.building_id
+             JOIN building_inputs bi ON bi.resource_id = bo.resource_id
+             WHERE r.step < ?2
+         )
+         SELECT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, b.dlc, b.building_type, b.min_critters, b.extractor, b.dupe_operated, b.passive_thermal, b.thermal_mass, b.heat_conductivity, MIN(r.step)
+         FROM reach r
+         JOIN buildings b ON b.id = r.building_id
+         GROUP BY b.id
+         ORDER BY 15, b.name",
+    )?;
+
+    let rows = stmt.query_map((resource, max_steps as i64), |row| {
+        Ok((building_from_row(row)?, row.get::<_, i64>(14)? as usize))
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Find resources needed, directly or upstream, to run `building_id`
+///
+/// The building's own inputs are at depth 1; the inputs of buildings producing
+/// them at 2, and so on up to `max_depth`. Sorted by the depth each resource is
+/// first needed at, then ID.
+pub fn get_transitive_inputs(
+    conn: &Connection,
+    building_id: &str,
+    max_depth: usize,
+) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE needs(resource_id, depth) AS (
+             SELECT resource_id, 1 FROM building_inputs WHERE building_id = ?1
+             UNION
+             SELECT bi.resource_id, n.depth + 1
+             FROM needs n
+             JOIN building_outputs bo ON bo.resource_id = n.resource_id AND bo.output_type = 'mass'
+             JOIN building_inputs bi ON bi.building_id = bo.building_id
+             WHERE n.depth < ?2
+         )
+         SELECT resource_id
+         FROM needs
+         GROUP BY resource_id
+         ORDER BY MIN(depth), resource_id",
+    )?;
+
+    let rows = stmt.query_map((building_id, max_depth as i64), |row| row.get(0))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Power of every building in the chain for `resource` at `rate`, computed entirely in SQL
+///
+/// Mirrors the calculator's defaults (the first mass producer per resource in
+/// `get_producers` order, extractors only for the target, cycles and chains
+/// deeper than 20 levels cut off) but ignores rate overrides, so it can be
+/// used to cross-check `calculator::total_power`. Returns
+/// `(building_id, building_name, power_watts_scaled)` per building.
+pub fn get_chain_power_breakdown(
+    conn: &Connection,
+    resource: &str,
+    rate: f64,
+) -> Result<Vec<(String, String, f64)>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE chain(resource_id, rate, depth, path, producer_id) AS (
+             SELECT ?1, ?2, 0, ',' || ?1 || ',',
+                    (SELECT bo.id FROM building_outputs bo
+                     WHERE bo.resource_id = ?1 AND bo.output_type = 'mass'
+                     ORDER BY bo.id LIMIT 1)
+             UNION ALL
+             SELECT bi.resource_id,
+                    bi.rate_kg_per_s * c.rate / p.rate_kg_per_s,
+                    c.depth + 1,
+                    c.path || bi.resource_id || ',',
+                    (SELECT bo.id FROM building_outputs bo
+                     JOIN buildings b ON b.id = bo.building_id
+                     WHERE bo.resource_id = bi.resource_id AND bo.output_type = 'mass' AND NOT b.extractor
+                     ORDER BY bo.id LIMIT 1)
+             FROM chain c
+             JOIN building_outputs p ON p.id = c.producer_id
+             JOIN building_inputs bi ON bi.building_id = p.building_id
+             WHERE c.depth < 20 AND instr(c.path, ',' || bi.resource_id || ',') = 0
+         )
+         SELECT b.id, b.name, SUM(b.power_watts * c.rate / p.rate_kg_per_s)
+         FROM chain c
+         JOIN building_outputs p ON p.id = c.producer_id
+         JOIN buildings b ON b.id = p.building_id
+         GROUP BY b.id
+         ORDER BY b.name",
+    )?;
+
+    let rows = stmt.query_map((resource, rate), |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Find building chains that turn `resource` back into itself
+///
+/// Each cycle is a list of `(building_id, output_resource)` steps: the first
+/// building consumes `resource`, each next one consumes the previous step's
+/// output, and the last step outputs `resource` again. Buildings appear at
+/// most once per cycle; cycles are at most `max_len` buildings long.
+pub fn find_cyclic_chains(
+    conn: &Connection,
+    resource: &str,
+    max_len: usize,
+) -> Result<Vec<Vec<(String, String)>>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE walk(resource_id, steps, path) AS (
+             SELECT bo.resource_id, 1, ',' || bi.building_id || ':' || bo.resource_id || ','
+             FROM building_inputs bi
+             JOIN building_outputs bo ON bo.building_id = bi.building_id
+             WHERE bi.resource_id = ?1
+             UNION ALL
+             SELECT bo.resource_id, w.steps + 1, w.path || bi.building_id || ':' || bo.resource_id || ','
+             FROM walk w
+             JOIN building_inputs bi ON bi.resource_id = w.resource_id
+             JOIN building_outputs bo ON bo.building_id = bi.building_id
+             WHERE w.steps < ?2
+               AND w.resource_id != ?1
+               AND instr(w.path, ',' || bi.building_id || ':') = 0
+         )
+         SELECT DISTINCT path, steps FROM walk WHERE resource_id = ?1 ORDER BY steps, path",
+    )?;
+
+    let rows = stmt.query_map((resource, max_len as i64), |row| row.get::<_, String>(0))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        let steps = row?
+            .split(',')
+            .filter_map(|step| step.split_once(':'))
+            .map(|(building, output)| (building.to_string(), output.to_string()))
+            .collect();
+        results.push(steps);
+    }
+    Ok(results)
+}
+
+/// List all unique resources that are outputs
+pub fn list_producible_resources(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT resource_id FROM building_outputs WHERE output_type = 'mass' ORDER BY resource_id",
+    )?;
+
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Count the rows in a table
+pub fn count_rows(conn: &Connection, table: &str) -> Result<usize> {
+    let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })?;
+    Ok(count as usize)
+}
+
+/// Power statistics over all buildings; all zero when the table is empty
+pub fn get_building_efficiency_stats(conn: &Connection) -> Result<EfficiencyStats> {
+    let stats = conn.query_row(
+        "SELECT COALESCE(MIN(power_watts), 0), COALESCE(MAX(power_watts), 0), COALESCE(AVG(power_watts), 0), COUNT(*)
+         FROM buildings",
+        [],
+        |row| {
+            Ok(EfficiencyStats {
+                min_power_w: row.get(0)?,
+                max_power_w: row.get(1)?,
+                mean_power_w: row.get(2)?,
+                total_building_count: row.get::<_, i64>(3)? as usize,
+            })
+        },
+    )?;
+    Ok(stats)
+}
+
+/// Compare each resource's total output rate across all buildings with its total input rate
+///
+/// Only mass outputs count as supply. Differences under 1e-9 kg/s count as balanced.
+pub fn analyze_resource_flow(conn: &Connection) -> Result<FlowAnalysis> {
+    let mut stmt = conn.prepare(
+        "SELECT resource_id, SUM(supply) - SUM(demand)
+         FROM (
+             SELECT resource_id, rate_kg_per_s AS supply, 0 AS demand FROM building_outputs WHERE output_type = 'mass'
+             UNION ALL
+             SELECT resource_id, 0, rate_kg_per_s FROM building_inputs
+         )
+         GROUP BY resource_id
+         ORDER BY resource_id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+    })?;
+
+    let mut analysis = FlowAnalysis::default();
+    for row in rows {
+        let (resource, net) = row?;
+        if net > 1e-9 {
+            analysis.over_supplied.push((resource, net));
+        } else if net < -1e-9 {
+            analysis.under_supplied.push((resource, net));
+        } else {
+            analysis.balanced.push(resource);
+        }
+    }
+    analysis.over_supplied.sort_by(|a, b| b.1.total_cmp(&a.1));
+    analysis.under_supplied.sort_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(analysis)
+}
+
+/// Database page size in bytes
+pub fn pragma_page_size(conn: &Connection) -> Result<usize> {
+    let size: i64 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+    Ok(size as usize)
+}
+
+/// Number of pages in the database file
+pub fn pragma_page_count(conn: &Connection) -> Result<usize> {
+    let count: i64 = conn.pragma_query_value(None, "page_count", |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Page cache size (positive = pages, negative = KiB)
+pub fn pragma_cache_size(conn: &Connection) -> Result<i64> {
+    Ok(conn.pragma_query_value(None, "cache_size", |row| row.get(0))?)
+}
+
+/// Current journal mode (e.g. "delete", "wal")
+pub fn pragma_journal_mode(conn: &Connection) -> Result<String> {
+    Ok(conn.pragma_query_value(None, "journal_mode", |row| row.get(0))?)
+}
+
+/// Set per-connection performance PRAGMAs; call before any other operation
+///
+/// `use_wal = false` leaves the journal mode as it is (WAL is stored in the
+/// database file, so it stays on once enabled).
+pub fn configure_connection(conn: &Connection, use_wal: bool) -> Result<()> {
+    if use_wal {
+        // Readers don't block the writer, and commits append to the log instead of rewriting pages
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    }
+    // With WAL, NORMAL only risks the last commits on power loss, never corruption; skips most fsyncs
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    // Sorts and temp tables for the recursive queries stay off disk
+    conn.pragma_update(None, "temp_store", "MEMORY")?;
+    // Memory-map up to 256 MiB so reads skip the read() syscall copy
+    conn.pragma_update(None, "mmap_size", 268_435_456)?;
+    Ok(())
+}
+
+/// Set the page cache size for this connection (positive = pages, negative = KiB)
+pub fn set_cache_size(conn: &Connection, pages: i64) -> Result<()> {
+    conn.pragma_update(None, "cache_size", pages)?;
+    Ok(())
+}
+
+/// List every building/resource edge as `(building_id, resource_id, direction, rate_kg_per_s)`
+///
+/// `direction` is "input" or "output". Only edges of buildings matching `filter` are listed.
+pub fn list_all_edges(
+    conn: &Connection,
+    filter: &BuildingFilter,
+) -> Result<Vec<(String, String, String, f64)>> {
+    let (sql, params) = apply_filter(
+        "SELECT e.building_id, e.resource_id, e.direction, e.rate_kg_per_s
+         FROM (
+             SELECT building_id, resource_id, 'input' AS direction, rate_kg_per_s FROM building_inputs
+             UNION ALL
+             SELECT building_id, resource_id, 'output' AS direction, rate_kg_per_s FROM building_outputs
+         ) e
+         JOIN buildings b ON b.id = e.building_id",
+        filter,
+    );
+    let mut stmt = conn.prepare(&format!("{} ORDER BY 1, 3, 2", sql))?;
+
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// List the buildings matching `filter`, ordered by name
+pub fn list_buildings_filtered(
+    conn: &Connection,
+    filter: &BuildingFilter,
+) -> Result<Vec<Building>> {
+    let (sql, params) = apply_filter(
+        "SELECT b.id, b.name, b.category, b.power_watts, b.heat_output_dtu, b.construction_time_s, b.dlc, b.building_type, b.min_critters, b.extractor, b.dupe_operated, b.passive_thermal, b.thermal_mass, b.heat_conductivity
+         FROM buildings b",
+        filter,
+    );
+    let mut stmt = conn.prepare(&format!("{} ORDER BY b.name", sql))?;
+
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), building_from_row)?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Append the WHERE clause for `filter` to a query over `buildings b`
+///
+/// Returns the query and its positional parameters, in order.
+pub fn apply_filter(sql: &str, filter: &BuildingFilter) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+    let clauses = filter_clauses(filter, |value| {
+        params.push(value.to_string());
+        format!("?{}", params.len())
+    });
+
+    if clauses.is_empty() {
+        (sql.to_string(), params)
+    } else {
+        (format!("{} WHERE {}", sql, clauses.join(" AND ")), params)
+    }
+}
+
+/// WHERE conditions for `filter`; `bind` turns each user value into SQL (a placeholder or literal)
+fn filter_clauses(filter: &BuildingFilter, mut bind: impl FnMut(&str) -> String) -> Vec<String> {
+    let mut clauses = Vec::new();
+
+    if let Some(category) = &filter.category {
+        clauses.push(format!("b.category = {} COLLATE NOCASE", bind(category)));
+    }
+    match filter.dlc.as_deref() {
+        Some(dlc) if dlc.eq_ignore_ascii_case("base") => clauses.push("b.dlc IS NULL".to_string()),
+        Some(dlc) => clauses.push(format!("b.dlc = {} COLLATE NOCASE", bind(dlc))),
+        None => {}
+    }
+    if let Some(text) = &filter.name_contains {
+        clauses.push(format!("b.name LIKE '%' || {} || '%'", bind(text)));
+    }
+    if let Some(since) = &filter.since {
+        // datetime() normalizes ISO 8601 input such as "2024-01-01T00:00:00Z" to the stored format
+        clauses.push(format!("b.updated_at > datetime({})", bind(since)));
+    }
+
+    clauses
+}
+
+/// Create a named view over the buildings matching `filter`, recorded in `user_views`
+///
+/// View definitions can't take parameters, so filter values are inlined as quoted literals.
+pub fn create_building_view(
+    conn: &Connection,
+    view_name: &str,
+    filter: &BuildingFilter,
+) -> Result<()> {
+    let valid_name = view_name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && view_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        anyhow::bail!(
+            "invalid view name '{}': use letters, digits and underscores",
+            view_name
+        );
+    }
+
+    let clauses = filter_clauses(filter, |value| format!("'{}'", value.replace('\'', "''")));
+    let mut definition = "SELECT b.* FROM buildings b".to_string();
+    if !clauses.is_empty() {
+        definition = format!("{} WHERE {}", definition, clauses.join(" AND "));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(&format!(
+        "DROP VIEW IF EXISTS {0}; CREATE VIEW {0} AS {1};",
+        view_name, definition
+    ))?;
+    tx.execute(
+        "INSERT OR REPLACE INTO user_views (name, definition) VALUES (?1, ?2)",
+        (view_name, &definition),
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Drop a view created by `create_building_view`; returns false if no such view was recorded
+pub fn drop_building_view(conn: &Connection, view_name: &str) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    let removed = tx.execute("DELETE FROM user_views WHERE name = ?1", [view_name])? > 0;
+    if removed {
+        tx.execute_batch(&format!("DROP VIEW IF EXISTS {};", view_name))?;
+    }
+    tx.commit()?;
+    Ok(removed)
+}
+
+/// Recreate every view recorded in `user_views`; returns how many were recreated
+pub fn recreate_user_views(conn: &Connection) -> Result<usize> {
+    let mut stmt = conn.prepare("SELECT name, definition FROM user_views")?;
+    let views = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (name, definition) in &views {
+        conn.execute_batch(&format!(
+            "DROP VIEW IF EXISTS {0}; CREATE VIEW {0} AS {1};",
+            name, definition
+        ))?;
+    }
+    Ok(views.len())
+}
+
+/// Find resources produced and/or consumed by many buildings
+///
+/// Returns `(resource_id, producer_count, consumer_count)` for every resource,
+/// most connected (by combined degree) first.
+pub fn find_hub_resources(conn: &Connection) -> Result<Vec<(String, usize, usize)>> {
+    let mut stmt = conn.prepare(
+        "SELECT resource_id,
+                COUNT(DISTINCT CASE WHEN direction = 'output' THEN building_id END) AS producers,
+                COUNT(DISTINCT CASE WHEN direction = 'input' THEN building_id END) AS consumers
+         FROM (
+             SELECT building_id, resource_id, 'input' AS direction FROM building_inputs
+             UNION ALL
+             SELECT building_id, resource_id, 'output' AS direction FROM building_outputs
+         )
+         GROUP BY resource_id
+         ORDER BY producers + consumers DESC, resource_id",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)? as usize,
+            row.get::<_, i64>(2)? as usize,
+        ))
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// List every resource ID referenced anywhere in the database
+pub fn list_resource_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT resource_id FROM building_inputs
+         UNION
+         SELECT resource_id FROM building_outputs
+         UNION
+         SELECT id FROM resources
+         ORDER BY 1",
+    )?;
+
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Document layout used by `export_toml` / `load_from_toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TomlDocument {
+    #[serde(default)]
+    buildings: Vec<TomlBuilding>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TomlBuilding {
+    id: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(default)]
+    power_watts: f64,
+    #[serde(default)]
+    heat_output_dtu: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    construction_time_s: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dlc: Option<String>,
+    #[serde(default = "default_building_type")]
+    building_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_critters: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    extractor: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    dupe_operated: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    passive_thermal: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thermal_mass: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heat_conductivity: Option<f64>,
+    #[serde(default)]
+    inputs: Vec<TomlFlow>,
+    #[serde(default)]
+    outputs: Vec<TomlFlow>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TomlFlow {
+    resource_id: String,
+    rate_kg_per_s: f64,
+    #[serde(
+        default = "default_output_type",
+        skip_serializing_if = "is_mass_output"
+    )]
+    output_type: String,
+}
+
+fn default_output_type() -> String {
+    BuildingOutputType::Mass.as_str().to_string()
+}
+
+fn is_mass_output(output_type: &str) -> bool {
+    BuildingOutputType::from_name(output_type) == BuildingOutputType::Mass
+}
+
+fn default_building_type() -> String {
+    BuildingType::Production.as_str().to_string()
+}
+
+/// Export buildings matching `filter` with their inputs and outputs as a human-editable TOML document
+pub fn export_toml(
+    conn: &Connection,
+    filter: &BuildingFilter,
+    mut writer: impl Write,
+) -> Result<()> {
+    let mut buildings: Vec<TomlBuilding> = list_buildings_filtered(conn, filter)?
+        .into_iter()
+        .map(|b| TomlBuilding {
+            id: b.id,
+            name: b.name,
+            category: b.category,
+            power_watts: b.power_watts,
+            heat_output_dtu: b.heat_output_dtu,
+            construction_time_s: b.construction_time_s,
+            dlc: b.dlc,
+            building_type: b.building_type.as_str().to_string(),
+            min_critters: b.min_critters,
+            extractor: b.extractor,
+            dupe_operated: b.dupe_operated,
+            passive_thermal: b.passive_thermal,
+            thermal_mass: b.thermal_mass,
+            heat_conductivity: b.heat_conductivity,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        })
+        .collect();
+
+    // Thrust, power and research outputs; everything else is mass
+    let mut stmt = conn.prepare("SELECT building_id, resource_id, output_type FROM building_outputs WHERE output_type != 'mass'")?;
+    let output_types = stmt
+        .query_map([], |row| {
+            Ok((
+                (row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+
+    for (building_id, resource_id, direction, rate_kg_per_s) in list_all_edges(conn, filter)? {
+        if let Some(b) = buildings.iter_mut().find(|b| b.id == building_id) {
+            if direction == "input" {
+                b.inputs.push(TomlFlow {
+                    resource_id,
+                    rate_kg_per_s,
+                    output_type: default_output_type(),
+                });
+            } else {
+                let output_type = output_types
+                    .get(&(building_id.clone(), resource_id.clone()))
+                    .cloned()
+                    .unwrap_or_else(default_output_type);
+                b.outputs.push(TomlFlow {
+                    resource_id,
+                    rate_kg_per_s,
+                    output_type,
+                });
+            }
+        }
+    }
+
+    let document = toml::to_string_pretty(&TomlDocument { buildings })?;
+    writer.write_all(document.as_bytes())?;
+    Ok(())
+}
+
+/// Load buildings from a TOML document written by `export_toml`
+///
+/// Each building in the document replaces the existing building and its I/O.
+/// Returns the number of buildings loaded.
+pub fn load_from_toml(conn: &Connection, mut reader: impl Read) -> Result<usize> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    load_document(conn, &toml::from_str(&text)?)
+}
+
+/// Single-building TOML document to fill in by hand and load with `load_from_toml`
+pub fn building_template_toml(id: &str, category: Option<&str>) -> Result<String> {
+    let document = TomlDocument {
+        buildings: vec![TomlBuilding {
+            id: id.to_string(),
+            name: id.to_string(),
+            category: category.map(str::to_string),
+            power_watts: 0.0,
+            heat_output_dtu: 0.0,
+            construction_time_s: None,
+            dlc: None,
+            building_type: default_building_type(),
+            min_critters: None,
+            extractor: false,
+            dupe_operated: false,
+            passive_thermal: false,
+            thermal_mass: None,
+            heat_conductivity: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }],
+    };
+    Ok(toml::to_string_pretty(&document)?)
+}
+
+fn load_document(conn: &Connection, document: &TomlDocument) -> Result<usize> {
+    let buildings: Vec<Building> = document
+        .buildings
+        .iter()
+        .map(|b| Building {
+            id: b.id.clone(),
+            name: b.name.clone(),
+            category: b.category.clone(),
+            power_watts: b.power_watts,
+            heat_output_dtu: b.heat_output_dtu,
+            construction_time_s: b.construction_time_s,
+            dlc: b.dlc.clone(),
+            building_type: BuildingType::from_name(&b.building_type),
+            min_critters: b.min_critters,
+            extractor: b.extractor,
+            dupe_operated: b.dupe_operated,
+            passive_thermal: b.passive_thermal,
+            thermal_mass: b.thermal_mass,
+            heat_conductivity: b.heat_conductivity,
+        })
+        .collect();
+
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    for b in &document.buildings {
+        inputs.extend(b.inputs.iter().map(|flow| BuildingInput {
+            building_id: b.id.clone(),
+            resource_id: flow.resource_id.clone(),
+            rate_kg_per_s: flow.rate_kg_per_s,
+        }));
+        outputs.extend(b.outputs.iter().map(|flow| BuildingOutput {
+            building_id: b.id.clone(),
+            resource_id: flow.resource_id.clone(),
+            rate_kg_per_s: flow.rate_kg_per_s,
+            output_type: BuildingOutputType::from_name(&flow.output_type),
+        }));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut delete_inputs = tx.prepare("DELETE FROM building_inputs WHERE building_id = ?1")?;
+        let mut delete_outputs =
+            tx.prepare("DELETE FROM building_outputs WHERE building_id = ?1")?;
+        for b in &buildings {
+            delete_inputs.execute([&b.id])?;
+            delete_outputs.execute([&b.id])?;
+        }
+    }
+    upsert_buildings_bulk(&tx, &buildings)?;
+    insert_building_inputs_bulk(&tx, &inputs)?;
+    insert_building_outputs_bulk(&tx, &outputs)?;
+    tx.commit()?;
+
+    Ok(document.buildings.len())
+}
+
+/// Write rate overrides, replacing any existing override for the same building/resource/direction
+pub fn apply_overrides(conn: &Connection, overrides: &[RateOverride]) -> Result<()> {
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    for o in overrides {
+        conn.execute(
+            "INSERT OR REPLACE INTO rate_overrides (building_id, resource_id, direction, rate_kg_per_s)
+             VALUES (?1, ?2, ?3, ?4)",
+            (&o.building_id, &o.resource_id, &o.direction, o.rate_kg_per_s),
+        )?;
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Scale every input and output rate of each `(building_id, factor)` in `table`
+///
+/// Factors apply to the extracted rates, saved in `rate_originals` the first
+/// time a building is scaled, so applying 2.0 then 1.25 leaves 1.25x.
+pub fn apply_efficiency_table(conn: &Connection, table: &[(String, f64)]) -> Result<()> {
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    for (building_id, factor) in table {
+        for (io_table, direction) in [("building_inputs", "input"), ("building_outputs", "output")]
+        {
+            conn.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO rate_originals (building_id, resource_id, direction, rate_kg_per_s)
+                     SELECT building_id, resource_id, ?2, rate_kg_per_s FROM {} WHERE building_id = ?1",
+                    io_table
+                ),
+                (building_id, direction),
+            )?;
+            conn.execute(
+                &format!(
+                    "UPDATE {} SET rate_kg_per_s = ?3 * (
+                         SELECT o.rate_kg_per_s FROM rate_originals o
+                         WHERE o.building_id = {0}.building_id AND o.resource_id = {0}.resource_id AND o.direction = ?2
+                     )
+                     WHERE building_id = ?1",
+                    io_table
+                ),
+                (building_id, direction, factor),
+            )?;
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Restore the rates saved by `apply_efficiency_table`, returning how many were restored
+pub fn revert_efficiency_table(conn: &Connection) -> Result<usize> {
+    let tx = conn
+        .is_autocommit()
+        .then(|| conn.unchecked_transaction())
+        .transpose()?;
+    let mut restored = 0;
+    for (io_table, direction) in [("building_inputs", "input"), ("building_outputs", "output")] {
+        restored += conn.execute(
+            &format!(
+                "UPDATE {0} SET rate_kg_per_s = (
+                     SELECT o.rate_kg_per_s FROM rate_originals o
+                     WHERE o.building_id = {0}.building_id AND o.resource_id = {0}.resource_id AND o.direction = ?1
+                 )
+                 WHERE EXISTS (
+                     SELECT 1 FROM rate_originals o
+                     WHERE o.building_id = {0}.building_id AND o.resource_id = {0}.resource_id AND o.direction = ?1
+                 )",
+                io_table
+            ),
+            [direction],
+        )?;
+    }
+    conn.execute("DELETE FROM rate_originals", [])?;
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(restored)
+}
+
+/// Get the override rate for a building input or output, if one is set
+pub fn get_rate_override(
+    conn: &Connection,
+    building_id: &str,
+    resource_id: &str,
+    direction: &str,
+) -> Result<Option<f64>> {
+    let rate = conn
+        .query_row(
+            "SELECT rate_kg_per_s FROM rate_overrides WHERE building_id = ?1 AND resource_id = ?2 AND direction = ?3",
+            (building_id, resource_id, direction),
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(rate)
+}
+
+/// List all rate overrides
+pub fn list_overrides(conn: &Connection) -> Result<Vec<RateOverride>> {
+    let mut stmt = conn.prepare(
+        "SELECT building_id, resource_id, direction, rate_kg_per_s FROM rate_overrides
+         ORDER BY building_id, direction, resource_id",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(RateOverride {
+            building_id: row.get(0)?,
+            resource_id: row.get(1)?,
+            direction: row.get(2)?,
+            rate_kg_per_s: row.get(3)?,
+        })
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Remove all rate overrides, returning how many were removed
+pub fn clear_overrides(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute("DELETE FROM rate_overrides", [])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn test_building(id: &str, power_watts: f64) -> Building {
+        Building {
+            id: id.to_string(),
+            name: id.to_string(),
+            category: None,
+            power_watts,
+            heat_output_dtu: 0.0,
+            construction_time_s: None,
+            dlc: None,
+            building_type: BuildingType::default(),
+            min_critters: None,
+            extractor: false,
+            dupe_operated: false,
+            passive_thermal: false,
+            thermal_mass: None,
+            heat_conductivity: None,
+        }
+    }
+
+    fn flows(items: &[(&str, f64)]) -> Vec<(String, f64)> {
+        items
+            .iter()
+            .map(|(r, rate)| (r.to_string(), *rate))
+            .collect()
+    }
+
+    #[test]
+    fn recipes_with_missing_inputs_or_outputs() {
+        let conn = test_db();
+        upsert_building(&conn, &test_building("MetalRefinery", -1200.0)).unwrap();
+
+        // Names sort as "Burn", "Iron", "Scrap", the order get_recipes_for_building returns
+        upsert_recipe(
+            &conn,
+            "MetalRefinery",
+            "Scrap",
+            &flows(&[("Dirt", 1.0)]),
+            &[],
+            Some(40.0),
+            None,
+        )
+        .unwrap();
+        upsert_recipe(
+            &conn,
+            "MetalRefinery",
+            "Burn",
+            &[],
+            &flows(&[("CarbonDioxide", 0.1)]),
+            None,
+            None,
+        )
+        .unwrap();
+        upsert_recipe(
+            &conn,
+            "MetalRefinery",
+            "Iron",
+            &flows(&[("IronOre", 2.5)]),
+            &flows(&[("Iron", 2.5)]),
+            Some(40.0),
+            None,
+        )
+        .unwrap();
+
+        let recipes = get_recipes_for_building(&conn, "MetalRefinery").unwrap();
+        let names: Vec<_> = recipes.iter().map(|(r, _, _)| r.name.as_str()).collect();
+        assert_eq!(names, ["Burn", "Iron", "Scrap"]);
+
+        let (burn, burn_in, burn_out) = &recipes[0];
+        assert!(burn_in.is_empty());
+        assert_eq!(burn_out.len(), 1);
+        assert_eq!(burn_out[0].resource_id, "CarbonDioxide");
+        assert_eq!(burn.cycle_time_s, None);
+
+        let (iron, iron_in, iron_out) = &recipes[1];
+        assert_eq!(iron.building_id, "MetalRefinery");
+        assert_eq!(iron.cycle_time_s, Some(40.0));
+        assert_eq!(
+            (iron_in[0].resource_id.as_str(), iron_in[0].rate_kg_per_s),
+            ("IronOre", 2.5)
+        );
+        assert_eq!(
+            (iron_out[0].resource_id.as_str(), iron_out[0].rate_kg_per_s),
+            ("Iron", 2.5)
+        );
+
+        let (_, scrap_in, scrap_out) = &recipes[2];
+        assert_eq!(scrap_in.len(), 1);
+        assert!(scrap_out.is_empty());
+
+        assert!(
+            get_recipes_for_building(&conn, "Unknown")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn upsert_building_updates_existing_columns() {
+        let conn = test_db();
+        upsert_building(&conn, &test_building("Electrolyzer", -120.0)).unwrap();
+        upsert_building(&conn, &test_building("Electrolyzer", -240.0)).unwrap();
+
+        let building = get_building(&conn, "Electrolyzer").unwrap().unwrap();
+        assert_eq!(building.power_watts, -240.0);
+        assert_eq!(count_rows(&conn, "buildings").unwrap(), 1);
+    }
+
+    #[test]
+    fn upsert_building_keeps_inputs() {
+        let conn = test_db();
+        upsert_building(&conn, &test_building("Electrolyzer", -120.0)).unwrap();
+        insert_building_inputs_bulk(
+            &conn,
+            &[BuildingInput {
+                building_id: "Electrolyzer".to_string(),
+                resource_id: "Water".to_string(),
+                rate_kg_per_s: 1.0,
+            }],
+        )
+        .unwrap();
+
+        upsert_building(&conn, &test_building("Electrolyzer", -240.0)).unwrap();
+
+        let inputs = get_building_inputs(&conn, "Electrolyzer").unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(
+            (inputs[0].resource_id.as_str(), inputs[0].rate_kg_per_s),
+            ("Water", 1.0)
+        );
+    }
+
+    #[test]
+    fn thermal_columns_round_trip() {
+        let conn = test_db();
+        let mut tile = test_building("InsulationTile", 0.0);
+        tile.passive_thermal = true;
+        tile.thermal_mass = Some(100.0);
+        tile.heat_conductivity = Some(0.01);
+        upsert_building(&conn, &tile).unwrap();
+
+        let building = get_building(&conn, "InsulationTile").unwrap().unwrap();
+        assert_eq!(building.thermal_mass, Some(100.0));
+        assert_eq!(building.heat_conductivity, Some(0.01));
+    }
+
+    #[test]
+    fn power_breakdown_matches_calculator_on_sample_data() {
+        let conn = test_db();
+        crate::load_sample_data(&conn).unwrap();
+
+        let resources = list_producible_resources(&conn).unwrap();
+        assert!(!resources.is_empty());
+        for resource in resources {
+            let sql_total: f64 = get_chain_power_breakdown(&conn, &resource, 1.0)
+                .unwrap()
+                .iter()
+                .map(|(_, _, power)| power)
+                .sum();
+            let options = crate::calculator::CalcOptions::default();
+            let chain =
+                crate::calculator::calculate_production_chain(&conn, &resource, 1.0, &options)
+                    .unwrap();
+            let calc_total = crate::calculator::total_power(&chain);
+            assert!(
+                (sql_total - calc_total).abs() < 1e-6,
+                "{}: SQL {} vs calculator {}",
+                resource,
+                sql_total,
+                calc_total
+            );
+        }
+    }
+
+    #[test]
+    fn power_breakdown_skips_non_mass_outputs_and_upstream_extractors() {
+        let conn = test_db();
+        let mut well = test_building("Well", -500.0);
+        well.extractor = true;
+        for building in [
+            test_building("Telescope", -100.0),
+            well,
+            test_building("Sieve", -120.0),
+            test_building("Electrolyzer", -120.0),
+        ] {
+            upsert_building(&conn, &building).unwrap();
+        }
+
+        // The research row and the extractor are inserted before the producers the calculator picks
+        let output = |building_id: &str, resource_id: &str, output_type| BuildingOutput {
+            building_id: building_id.to_string(),
+            resource_id: resource_id.to_string(),
+            rate_kg_per_s: 1.0,
+            output_type,
+        };
+        insert_building_outputs_bulk(
+            &conn,
+            &[
+                output("Telescope", "Oxygen", BuildingOutputType::Research),
+                output("Well", "Water", BuildingOutputType::Mass),
+                output("Sieve", "Water", BuildingOutputType::Mass),
+                output("Electrolyzer", "Oxygen", BuildingOutputType::Mass),
+            ],
+        )
+        .unwrap();
+        let input = |building_id: &str, resource_id: &str| BuildingInput {
+            building_id: building_id.to_string(),
+            resource_id: resource_id.to_string(),
+            rate_kg_per_s: 1.0,
+        };
+        insert_building_inputs_bulk(
+            &conn,
+            &[
+                input("Electrolyzer", "Water"),
+                input("Sieve", "PollutedWater"),
+            ],
+        )
+        .unwrap();
+
+        let breakdown = get_chain_power_breakdown(&conn, "Oxygen", 1.0).unwrap();
+        let ids: Vec<_> = breakdown.iter().map(|(id, _, _)| id.as_str()).collect();
+        assert_eq!(ids, ["Electrolyzer", "Sieve"]);
+
+        let options = crate::calculator::CalcOptions::default();
+        let chain =
+            crate::calculator::calculate_production_chain(&conn, "Oxygen", 1.0, &options).unwrap();
+        let sql_total: f64 = breakdown.iter().map(|(_, _, power)| power).sum();
+        assert_eq!(sql_total, crate::calculator::total_power(&chain));
+    }
+
+    #[test]
+    fn rename_building_cascades() {
+        let conn = test_db();
+        upsert_building(&conn, &test_building("Electrolyzer", -120.0)).unwrap();
+        insert_building_inputs_bulk(
+            &conn,
+            &[BuildingInput {
+                building_id: "Electrolyzer".to_string(),
+                resource_id: "Water".to_string(),
+                rate_kg_per_s: 1.0,
+            }],
+        )
+        .unwrap();
+        insert_building_outputs_bulk(
+            &conn,
+            &[BuildingOutput {
+                building_id: "Electrolyzer".to_string(),
+                resource_id: "Oxygen".to_string(),
+                rate_kg_per_s: 0.888,
+                output_type: BuildingOutputType::Mass,
+            }],
+        )
+        .unwrap();
+        upsert_recipe(
+            &conn,
+            "Electrolyzer",
+            "Split",
+            &flows(&[("Water", 1.0)]),
+            &flows(&[("Oxygen", 0.888)]),
+            None,
+            None,
+        )
+        .unwrap();
+        apply_overrides(
+            &conn,
+            &[RateOverride {
+                building_id: "Electrolyzer".to_string(),
+                resource_id: "Oxygen".to_string(),
+                direction: "output".to_string(),
+                rate_kg_per_s: 0.9,
+            }],
+        )
+        .unwrap();
+
+        rename_building(&conn, "Electrolyzer", "ElectrolyzerV2").unwrap();
+
+        assert!(get_building(&conn, "Electrolyzer").unwrap().is_none());
+        assert!(get_building(&conn, "ElectrolyzerV2").unwrap().is_some());
+        assert!(
+            get_building_inputs(&conn, "Electrolyzer")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            get_building_inputs(&conn, "ElectrolyzerV2").unwrap().len(),
+            1
+        );
+        assert_eq!(
+            get_building_outputs(&conn, "ElectrolyzerV2").unwrap().len(),
+            1
+        );
+        let recipes = get_recipes_for_building(&conn, "ElectrolyzerV2").unwrap();
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].0.building_id, "ElectrolyzerV2");
+        assert!(
+            get_recipes_for_building(&conn, "Electrolyzer")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            get_rate_override(&conn, "ElectrolyzerV2", "Oxygen", "output").unwrap(),
+            Some(0.9)
+        );
+        assert_eq!(
+            get_rate_override(&conn, "Electrolyzer", "Oxygen", "output").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rename_building_rejects_unknown_or_taken_ids() {
+        let conn = test_db();
+        upsert_building(&conn, &test_building("Electrolyzer", -120.0)).unwrap();
+        upsert_building(&conn, &test_building("WaterPurifier", -120.0)).unwrap();
+
+        let missing = rename_building(&conn, "Unknown", "Renamed").unwrap_err();
+        assert!(missing.to_string().contains("not found"), "{}", missing);
+        assert!(get_building(&conn, "Renamed").unwrap().is_none());
+
+        let taken = rename_building(&conn, "Electrolyzer", "WaterPurifier").unwrap_err();
+        assert!(taken.to_string().contains("already exists"), "{}", taken);
+        assert!(get_building(&conn, "Electrolyzer").unwrap().is_some());
+    }
+
+    #[test]
+    fn building_template_loads_through_load_from_toml() {
+        let conn = test_db();
+        let template = building_template_toml("Compressor", Some("Oxygen")).unwrap();
+        assert_eq!(load_from_toml(&conn, template.as_bytes()).unwrap(), 1);
+
+        let building = get_building(&conn, "Compressor").unwrap().unwrap();
+        assert_eq!(building.category.as_deref(), Some("Oxygen"));
+        assert_eq!(building.building_type, BuildingType::Production);
+
+        // Filling in the empty lists the way a user would still loads
+        let filled = template
+            .replace(
+                "inputs = []",
+                r#"inputs = [{ resource_id = "Water", rate_kg_per_s = 1.0 }]"#,
+            )
+            .replace(
+                "outputs = []",
+                r#"outputs = [{ resource_id = "Oxygen", rate_kg_per_s = 0.9 }]"#,
+            );
+        assert_eq!(load_from_toml(&conn, filled.as_bytes()).unwrap(), 1);
+        let inputs = get_building_inputs(&conn, "Compressor").unwrap();
+        assert_eq!(
+            (inputs[0].resource_id.as_str(), inputs[0].rate_kg_per_s),
+            ("Water", 1.0)
+        );
+        let outputs = get_building_outputs(&conn, "Compressor").unwrap();
+        assert_eq!(
+            (outputs[0].resource_id.as_str(), outputs[0].rate_kg_per_s),
+            ("Oxygen", 0.9)
+        );
+    }
+}
@@ -3,16 +3,33 @@
 //! Parses decompiled C# source from Assembly-CSharp.dll to extract
 //! building definitions, inputs, outputs, and power requirements.
 
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use quick_xml::events::Event;
+use quick_xml::{Reader, XmlVersion};
 use regex::Regex;
 use rusqlite::Connection;
 use walkdir::WalkDir;
 
 use crate::db;
-use crate::models::{Building, BuildingInput, BuildingOutput};
+use crate::models::{
+    Building, BuildingInput, BuildingOutput, BuildingOutputType, BuildingType, Resource,
+};
+
+/// Pattern: `inputElement = SimHashes.ToxicSand`, shared by several parsers and compiled once
+static INPUT_ELEMENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"inputElement\s*=\s*SimHashes\.(\w+)").unwrap());
+/// Pattern: `outputElement = SimHashes.Ethanol`
+static OUTPUT_ELEMENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"outputElement\s*=\s*SimHashes\.(\w+)").unwrap());
+/// Pattern: `fabricator.workTime = 40f`
+static WORK_TIME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"workTime\s*=\s*([\d.]+)f?").unwrap());
 
 /// Extracted building data before database insertion
 #[derive(Debug, Default)]
@@ -20,12 +37,94 @@ struct ExtractedBuilding {
     id: String,
     power_watts: f64,
     heat_dtu: f64,
+    category: Option<String>,
+    building_type: BuildingType,
+    dlc: Option<String>,
+    min_critters: Option<u32>,
+    extractor: bool,
+    dupe_operated: bool,
+    passive_thermal: bool,
+    thermal_mass: Option<f64>,
+    heat_conductivity: Option<f64>,
+    thrust_n: Option<f64>,
+    research_points: Option<f64>, // Points per cycle from telescopes and space science buildings
+    fertilizer_kg_per_crop: Option<f64>,
     inputs: Vec<(String, f64)>,  // (element, rate_kg_s)
     outputs: Vec<(String, f64)>, // (element, rate_kg_s)
+    recipes: Vec<ExtractedRecipe>,
+}
+
+/// A `ComplexRecipe` fabricated by a building
+#[derive(Debug, Default)]
+struct ExtractedRecipe {
+    name: String,
+    inputs: Vec<(String, f64)>,  // (element, rate_kg_s)
+    outputs: Vec<(String, f64)>, // (element, rate_kg_s)
+    cycle_time_s: f64,           // Seconds per fabrication; rates are amounts divided by this
+    recipe_type: Option<String>, // "Food" for cooking stations
+}
+
+/// Recipe duration when the config uses a named constant such as
+/// `FOODSTUFF.RECIPES.STANDARD_COOK_TIME` instead of a literal
+const DEFAULT_RECIPE_TIME_S: f64 = 50.0;
+
+/// Specific heat capacities (DTU/g/°C) used to rate heat exchangers, whose pipes may carry any
+/// fluid; the stored heat removal is for water (liquid) or oxygen (gas)
+const WATER_SPECIFIC_HEAT: f64 = 4.179;
+const OXYGEN_SPECIFIC_HEAT: f64 = 1.005;
+
+/// Length of one game cycle in seconds, for per-cycle critter figures
+const CYCLE_LENGTH_S: f64 = 600.0;
+
+/// Normalize an element/tag ID so `SimHashes.Water`, `"Water"` and `"water"`
+/// all end up as the same `resource_id`
+///
+/// Trims whitespace, uppercases the first letter, and strips a trailing
+/// `Element` or `Tag` suffix (unless that would leave nothing).
+pub fn normalize_element_id(raw: &str) -> String {
+    let trimmed = raw.trim();
+
+    let mut chars = trimmed.chars();
+    let mut id = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => return String::new(),
+    };
+
+    for suffix in ["Element", "Tag"] {
+        if id.len() > suffix.len() && id.ends_with(suffix) {
+            id.truncate(id.len() - suffix.len());
+            break;
+        }
+    }
+
+    id
+}
+
+/// Detect a DLC directory segment (e.g. `SpacedOut!/`, `Expansion1/`, `DLC2/`)
+/// in a config path relative to the decompiled source root
+fn detect_dlc(relative_path: &Path) -> Option<String> {
+    relative_path
+        .parent()?
+        .components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .find(|segment| {
+            let lower = segment.to_lowercase();
+            let numbered_dlc = lower
+                .strip_prefix("dlc")
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+            lower.starts_with("spacedout") || lower.starts_with("expansion") || numbered_dlc
+        })
+        .map(|segment| segment.to_string())
 }
 
-/// Find all *Config.cs files that likely define buildings
-pub fn find_config_files(decompiled_dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+/// Whether a config defines a crop: `class MealwoodConfig : PlantConfig` or `: WildPlant`
+fn is_plant_config(content: &str) -> Result<bool> {
+    let plant_re = Regex::new(r"class\s+\w+\s*:\s*(?:PlantConfig|WildPlant)\b")?;
+    Ok(plant_re.is_match(content))
+}
+
+/// Find all *Config.cs files that likely define buildings, with the DLC each belongs to
+pub fn find_config_files(decompiled_dir: &Path) -> Result<Vec<(PathBuf, Option<String>)>> {
     let mut configs = Vec::new();
 
     for entry in WalkDir::new(decompiled_dir)
@@ -34,12 +133,16 @@ pub fn find_config_files(decompiled_dir: &Path) -> Result<Vec<std::path::PathBuf
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if path.extension().map_or(false, |ext| ext == "cs") {
+        if path.extension().is_some_and(|ext| ext == "cs") {
             let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
             if filename.ends_with("Config.cs") {
                 let content = fs::read_to_string(path).unwrap_or_default();
-                if content.contains("IBuildingConfig") || content.contains("CreateBuildingDef") {
-                    configs.push(path.to_path_buf());
+                let is_critter = content.contains("Diet.Info");
+                let is_building =
+                    content.contains("IBuildingConfig") || content.contains("CreateBuildingDef");
+                if is_building || is_critter || is_plant_config(&content)? {
+                    let relative = path.strip_prefix(decompiled_dir).unwrap_or(path);
+                    configs.push((path.to_path_buf(), detect_dlc(relative)));
                 }
             }
         }
@@ -48,12 +151,224 @@ pub fn find_config_files(decompiled_dir: &Path) -> Result<Vec<std::path::PathBuf
     Ok(configs)
 }
 
+/// Parse `ComplexRecipe` definitions (fabricators, cooking stations, etc.)
+///
+/// Pattern:
+/// ```text
+/// ComplexRecipe.RecipeElement[] array = new ComplexRecipe.RecipeElement[] {
+///     new ComplexRecipe.RecipeElement("Meat", 2f) };
+/// ComplexRecipe.RecipeElement[] array2 = ...;
+/// new ComplexRecipe(ComplexRecipeManager.MakeRecipeID(ID, array, array2), array, array2) { time = 50f, ... }
+/// ```
+/// Amounts are per recipe run, converted to kg/s using the recipe time.
+fn parse_complex_recipes(content: &str) -> Result<Vec<ExtractedRecipe>> {
+    let array_re = Regex::new(
+        r"(?s)ComplexRecipe\.RecipeElement\[\]\s+(\w+)\s*=\s*new\s+ComplexRecipe\.RecipeElement\[\]\s*\{(.*?)\};",
+    )?;
+    let element_re = Regex::new(
+        r#"RecipeElement\s*\(\s*(?:"(\w+)"|(\w+?)(?:Config)?\.ID|(?:SimHashes\.)?(\w+)(?:\.CreateTag\(\))?)\s*,\s*([\d.]+)f?"#,
+    )?;
+    let recipe_re = Regex::new(
+        r"(?s)new\s+ComplexRecipe\s*\((?:[^;{]*?),\s*(\w+)\s*,\s*(\w+)\s*\)\s*\{(.*?)\}",
+    )?;
+    let time_re = Regex::new(r"time\s*=\s*([\d.]+)f?")?;
+    // Fabricators like the Microbe Musher set one duration on their workable
+    // Pattern: fabricator.workTime = 40f or complexFabricatorWorkable.workTime = 40f
+    let work_time = WORK_TIME_RE
+        .captures(content)
+        .and_then(|t| t[1].parse::<f64>().ok())
+        .filter(|t| *t > 0.0);
+
+    let mut arrays: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+    for cap in array_re.captures_iter(content) {
+        let elements = element_re
+            .captures_iter(&cap[2])
+            .filter_map(|e| {
+                let id = e.get(1).or(e.get(2)).or(e.get(3))?.as_str().to_string();
+                Some((id, e[4].parse::<f64>().unwrap_or(0.0)))
+            })
+            .collect();
+        arrays.insert(cap[1].to_string(), elements);
+    }
+
+    let mut recipes = Vec::new();
+    for cap in recipe_re.captures_iter(content) {
+        let (Some(inputs), Some(outputs)) = (arrays.get(&cap[1]), arrays.get(&cap[2])) else {
+            continue;
+        };
+
+        let time = time_re
+            .captures(&cap[3])
+            .and_then(|t| t[1].parse::<f64>().ok())
+            .filter(|t| *t > 0.0)
+            .or(work_time)
+            .unwrap_or(DEFAULT_RECIPE_TIME_S);
+        let per_second = |elements: &Vec<(String, f64)>| -> Vec<(String, f64)> {
+            elements
+                .iter()
+                .map(|(e, amount)| (e.clone(), amount / time))
+                .collect()
+        };
+
+        recipes.push(ExtractedRecipe {
+            name: outputs
+                .first()
+                .map(|(e, _)| normalize_element_id(e))
+                .unwrap_or_default(),
+            inputs: per_second(inputs),
+            outputs: per_second(outputs),
+            cycle_time_s: time,
+            recipe_type: None,
+        });
+    }
+
+    Ok(recipes)
+}
+
+/// Parse a food item's calories from its entity config
+///
+/// Pattern: `new EdiblesManager.FoodInfo("CookedMeat", "", 4000000f, 3, ...)`.
+/// The game stores calories ×1000, so the result is in kcal.
+fn parse_food_info(content: &str) -> Result<Option<(String, f64)>> {
+    let food_re = Regex::new(
+        r#"new\s+EdiblesManager\.FoodInfo\s*\(\s*(?:"(\w+)"|\w+)\s*,(?:\s*"[^"]*"\s*,)?\s*([\d.]+)f?"#,
+    )?;
+    let Some(cap) = food_re.captures(content) else {
+        return Ok(None);
+    };
+
+    let id = match cap.get(1) {
+        Some(id) => id.as_str().to_string(),
+        None => match Regex::new(r#"const\s+string\s+ID\s*=\s*"(\w+)""#)?.captures(content) {
+            Some(id_cap) => id_cap[1].to_string(),
+            None => return Ok(None),
+        },
+    };
+    let calories = cap[2].parse::<f64>().unwrap_or(0.0) / 1000.0;
+
+    Ok(Some((normalize_element_id(&id), calories)))
+}
+
+/// Find entity configs that define edible items
+fn find_food_configs(decompiled_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(decompiled_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.ends_with("Config.cs"))
+                && fs::read_to_string(path).is_ok_and(|c| c.contains("EdiblesManager.FoodInfo"))
+        })
+        .collect()
+}
+
+/// Find elements XML files (`<Element id=... />` definitions) under the decompiled tree
+fn find_element_files(decompiled_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(decompiled_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "xml")
+                && fs::read_to_string(path).is_ok_and(|c| c.contains("<Element"))
+        })
+        .collect()
+}
+
+/// Parse building tooltip descriptions from the decompiled `STRINGS` classes
+///
+/// Pattern: `public class ELECTROLYZER { ... public static LocString DESC = "..."; }`
+/// Returns descriptions keyed by the upper-cased building ID.
+fn parse_building_strings(filepath: &Path) -> Result<HashMap<String, String>> {
+    let content = fs::read_to_string(filepath)
+        .with_context(|| format!("Failed to read {}", filepath.display()))?;
+
+    let desc_re = Regex::new(
+        r#"public\s+class\s+(\w+)\s*\{[^{}]*?LocString\s+DESC\s*=\s*"((?:[^"\\]|\\.)*)""#,
+    )?;
+    let link_re = Regex::new(r#"<link=\\?"\w*\\?">|</link>"#)?;
+
+    let mut descriptions = HashMap::new();
+    for cap in desc_re.captures_iter(&content) {
+        let text = link_re.replace_all(&cap[2], "").replace("\\n", " ");
+        descriptions.insert(cap[1].to_string(), text);
+    }
+    Ok(descriptions)
+}
+
+/// Find decompiled `STRINGS` source files (e.g. `STRINGS/BUILDINGS.cs`)
+fn find_strings_files(decompiled_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(decompiled_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "cs")
+                && path.components().any(|c| c.as_os_str() == "STRINGS")
+        })
+        .collect()
+}
+
+/// Detect the game version the decompiled source was built from
+///
+/// Looks in `AssemblyInfo.cs` / `GlobalAssets.cs` (and `KleiVersion.cs`, where
+/// newer builds keep it) for a changelist number, reported as e.g. `"CS-469300"`,
+/// falling back to the assembly version string.
+pub fn detect_game_version(decompiled_dir: &Path) -> Result<Option<String>> {
+    const VERSION_FILES: [&str; 3] = ["AssemblyInfo.cs", "GlobalAssets.cs", "KleiVersion.cs"];
+
+    // Pattern: public static uint ChangeList = 469300u;
+    let changelist_re = Regex::new(r"ChangeList\s*=\s*(\d+)u?")?;
+    // Pattern: [assembly: AssemblyInformationalVersion("CS-469300")] or AssemblyVersion("1.0.0.0")
+    let assembly_re = Regex::new(r#"Assembly(?:Informational)?Version\s*\(\s*"([^"]+)""#)?;
+
+    let mut assembly_version = None;
+    let files = WalkDir::new(decompiled_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| VERSION_FILES.iter().any(|name| e.file_name() == *name));
+
+    for entry in files {
+        let content = fs::read_to_string(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        if let Some(cap) = changelist_re.captures(&content) {
+            return Ok(Some(format!("CS-{}", &cap[1])));
+        }
+        if assembly_version.is_none()
+            && let Some(cap) = assembly_re.captures(&content)
+        {
+            assembly_version = Some(cap[1].to_string());
+        }
+    }
+
+    Ok(assembly_version)
+}
+
+/// Drop the explicit casts newer dnSpy versions put on numeric literals
+///
+/// `component.EnergyConsumptionWhenActive = (float)120` becomes `... = 120`, so
+/// every `[\d.]+f?` pattern matches both decompiler styles.
+fn strip_literal_casts(content: &str) -> Result<String> {
+    let cast_re = Regex::new(r"\((?:float|double)\)\s*([\d.]+)")?;
+    Ok(cast_re.replace_all(content, "$1").into_owned())
+}
+
 /// Parse a single building config file
-fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
+fn parse_building_config(filepath: &Path, dlc: Option<&str>) -> Result<Option<ExtractedBuilding>> {
     let content = fs::read_to_string(filepath)
         .with_context(|| format!("Failed to read {}", filepath.display()))?;
+    let content = strip_literal_casts(&content)?;
 
-    let mut building = ExtractedBuilding::default();
+    let mut building = ExtractedBuilding {
+        dlc: dlc.map(str::to_string),
+        ..Default::default()
+    };
 
     // Extract building ID - multiple patterns
 
@@ -84,6 +399,18 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
         return Ok(None);
     }
 
+    // Critters (Pacu, Hatch, ...) are ranched like buildings: food in, waste out
+    if content.contains("Diet.Info") {
+        parse_critter_diet(&content, &mut building)?;
+        return Ok(Some(building));
+    }
+
+    // Crops (Mealwood, Thimble Reed, ...) are farmed like buildings: fertilizer in, harvest out
+    if is_plant_config(&content)? {
+        parse_plant(&content, &mut building)?;
+        return Ok(Some(building));
+    }
+
     // Extract power consumption
     // Pattern: EnergyConsumptionWhenActive = 120f
     let power_re = Regex::new(r"EnergyConsumptionWhenActive\s*=\s*([\d.]+)f?")?;
@@ -105,6 +432,41 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
         building.heat_dtu += cap[1].parse::<f64>().unwrap_or(0.0) * 1000.0; // kW to DTU/s
     }
 
+    // Ranch and shearing stations need critters in the room to operate
+    // Pattern: critterCount = 4
+    let critter_re = Regex::new(r"critterCount\s*=\s*(\d+)")?;
+    if let Some(cap) = critter_re.captures(&content) {
+        building.min_critters = cap[1].parse().ok();
+    }
+
+    // Rocket modules and launch pads (base game and Spaced Out!) belong to the space industry
+    // Pattern: go.AddOrGet<RocketModuleCluster>() or ExtendBuildingToRocketModule(...) or LaunchPad
+    let space_re =
+        Regex::new(r"\bRocketModule(?:Cluster)?\b|ExtendBuildingToRocketModule|\bLaunchPad\b")?;
+    if space_re.is_match(&content) {
+        building.category = Some("Space".to_string());
+    }
+
+    // Skill stations modify duplicant attributes; any I/O they have is parsed as usual
+    // Pattern: SkillStation / EQUIPMENT_CRAFTERS ... new AttributeModifier(Db.Get().Attributes.Machinery.Id, 2f, ...)
+    let skill_station_re = Regex::new(r"\bSkillStation|EQUIPMENT_CRAFTERS")?;
+    let attribute_re = Regex::new(r"new\s+AttributeModifier\s*\(")?;
+    if skill_station_re.is_match(&content) && attribute_re.is_match(&content) {
+        building.category = Some("Skills".to_string());
+        building.building_type = BuildingType::Services;
+    }
+
+    // Fabricators and cooking stations define their conversions as ComplexRecipes
+    // Kitchen buildings (Microbe Musher, Electric Grill, Gas Range, Juicer) cook FOODSTUFF recipes
+    building.recipes = parse_complex_recipes(&content)?;
+    let food_re = Regex::new(r"FOODSTUFF\.|GameTags\.Edible|Tag\.Food|CookingStation|Juicer")?;
+    if !building.recipes.is_empty() && food_re.is_match(&content) {
+        building.category = Some("Food".to_string());
+        for recipe in &mut building.recipes {
+            recipe.recipe_type = Some("Food".to_string());
+        }
+    }
+
     // Extract consumed elements - multiple patterns
 
     // Pattern 1: ConsumedElement(new Tag("Water"), 1f, true)
@@ -117,8 +479,7 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
     }
 
     // Pattern 2: ConsumedElement(SimHashes.Water, 1f) - older format
-    let consumed_hash_re =
-        Regex::new(r"ConsumedElement\s*\(\s*SimHashes\.(\w+)\s*,\s*([\d.]+)f?")?;
+    let consumed_hash_re = Regex::new(r"ConsumedElement\s*\(\s*SimHashes\.(\w+)\s*,\s*([\d.]+)f?")?;
     for cap in consumed_hash_re.captures_iter(&content) {
         let element = cap[1].to_string();
         let rate = cap[2].parse::<f64>().unwrap_or(0.0);
@@ -128,8 +489,9 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
     }
 
     // Pattern 2b: ConsumedElement(GameTagExtensions.Create(SimHashes.Water), 1f, true)
-    let consumed_gametag_re =
-        Regex::new(r"ConsumedElement\s*\(\s*GameTagExtensions\.Create\(SimHashes\.(\w+)\)\s*,\s*([\d.]+)f?")?;
+    let consumed_gametag_re = Regex::new(
+        r"ConsumedElement\s*\(\s*GameTagExtensions\.Create\(SimHashes\.(\w+)\)\s*,\s*([\d.]+)f?",
+    )?;
     for cap in consumed_gametag_re.captures_iter(&content) {
         let element = cap[1].to_string();
         let rate = cap[2].parse::<f64>().unwrap_or(0.0);
@@ -138,10 +500,23 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
         }
     }
 
+    // Pattern 2c: craft stations (Rock Crusher, Ore Scrubber) list every consumed material
+    // Example: new ElementConverter.InputElement(GameTagExtensions.Create(SimHashes.Sand), 0.5f)
+    let input_element_re = Regex::new(
+        r"new\s+ElementConverter\.InputElement\s*\(\s*[^,]*?SimHashes\.(\w+)[^,]*,\s*([\d.]+)f?",
+    )?;
+    for cap in input_element_re.captures_iter(&content) {
+        let element = cap[1].to_string();
+        let rate = cap[2].parse::<f64>().unwrap_or(0.0);
+        if !building.inputs.iter().any(|(e, _)| e == &element) {
+            building.inputs.push((element, rate));
+        }
+    }
+
     // Pattern 3: CreateSimpleFormula(input, inputRate, capacity, output, outputRate, ...) for generators
     // Example: CreateSimpleFormula(SimHashes.Carbon.CreateTag(), 1f, 600f, SimHashes.CarbonDioxide, 0.02f, ...)
     let formula_re = Regex::new(
-        r"CreateSimpleFormula\s*\(\s*SimHashes\.(\w+)\.CreateTag\(\)\s*,\s*([\d.]+)f?\s*,\s*[\d.]+f?\s*,\s*SimHashes\.(\w+)\s*,\s*([\d.]+)f?"
+        r"CreateSimpleFormula\s*\(\s*SimHashes\.(\w+)\.CreateTag\(\)\s*,\s*([\d.]+)f?\s*,\s*[\d.]+f?\s*,\s*SimHashes\.(\w+)\s*,\s*([\d.]+)f?",
     )?;
     for cap in formula_re.captures_iter(&content) {
         let in_element = cap[1].to_string();
@@ -173,15 +548,18 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
         let rate = cap[1].parse::<f64>().unwrap_or(0.0);
 
         // Determine element type from Configuration or ConduitType
-        let element = if content.contains("Configuration.AllGas") || content.contains("ConduitType.Gas") {
-            "Gas".to_string()
-        } else if content.contains("Configuration.AllLiquid") || content.contains("ConduitType.Liquid") {
-            "Liquid".to_string()
-        } else if let Some(elem_cap) = Regex::new(r"SimHashes\.(\w+)")?.captures(&content) {
-            elem_cap[1].to_string()
-        } else {
-            "Unknown".to_string()
-        };
+        let element =
+            if content.contains("Configuration.AllGas") || content.contains("ConduitType.Gas") {
+                "Gas".to_string()
+            } else if content.contains("Configuration.AllLiquid")
+                || content.contains("ConduitType.Liquid")
+            {
+                "Liquid".to_string()
+            } else if let Some(elem_cap) = Regex::new(r"SimHashes\.(\w+)")?.captures(&content) {
+                elem_cap[1].to_string()
+            } else {
+                "Unknown".to_string()
+            };
 
         if !building.inputs.iter().any(|(e, _)| e == &element) {
             building.inputs.push((element, rate));
@@ -195,9 +573,16 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
         let rate = cap[1].parse::<f64>().unwrap_or(0.0);
 
         // Determine element type from capacityTag or conduitType
-        let element = if let Some(tag_cap) = Regex::new(r"capacityTag\s*=\s*(?:ElementLoader\.FindElementByHash\()?SimHashes\.(\w+)")?.captures(&content) {
+        let element = if let Some(tag_cap) = Regex::new(
+            r"capacityTag\s*=\s*(?:ElementLoader\.FindElementByHash\()?SimHashes\.(\w+)",
+        )?
+        .captures(&content)
+        {
             tag_cap[1].to_string()
-        } else if let Some(tag_cap) = Regex::new(r"capacityTag\s*=\s*GameTagExtensions\.Create\(SimHashes\.(\w+)\)")?.captures(&content) {
+        } else if let Some(tag_cap) =
+            Regex::new(r"capacityTag\s*=\s*GameTagExtensions\.Create\(SimHashes\.(\w+)\)")?
+                .captures(&content)
+        {
             tag_cap[1].to_string()
         } else if content.contains("ConduitType.Gas") {
             "Gas".to_string()
@@ -235,37 +620,471 @@ fn parse_building_config(filepath: &Path) -> Result<Option<ExtractedBuilding>> {
         }
     }
 
+    // SublimateMass buildings (ice melting, dry ice sublimation) passively turn a solid into a gas
+    // Pattern: SublimateMass.sublimateRate = 0.1f; ... outputElement = SimHashes.CarbonDioxide
+    let sublimate_re = Regex::new(r"SublimateMass\.sublimateRate\s*=\s*([\d.]+)f?")?;
+    if let Some(cap) = sublimate_re.captures(&content) {
+        let rate = cap[1].parse::<f64>().unwrap_or(0.0);
+
+        if let Some(out_cap) = OUTPUT_ELEMENT_RE.captures(&content) {
+            let element = out_cap[1].to_string();
+            if !building.outputs.iter().any(|(e, _)| e == &element) {
+                building.outputs.push((element, rate));
+            }
+        }
+
+        // Mass is conserved, so the solid is consumed at the sublimation rate
+        if let Some(in_cap) = INPUT_ELEMENT_RE.captures(&content) {
+            let element = in_cap[1].to_string();
+            if !building.inputs.iter().any(|(e, _)| e == &element) {
+                building.inputs.push((element, rate));
+            }
+        }
+
+        // Passive conversion - no active power draw
+        building.power_watts = 0.0;
+    }
+
+    // Fermentation buildings (Ethanol Distillery) convert a batch of mass over a fixed time
+    // Pattern: convertMass = 1f; ConvertTime = 2f; outputElement = SimHashes.Ethanol;
+    //          byproductElement = SimHashes.CarbonDioxide; byproductMass = 0.33f
+    let convert_mass_re = Regex::new(r"convertMass\s*=\s*([\d.]+)f?")?;
+    let convert_time_re = Regex::new(r"ConvertTime\s*=\s*([\d.]+)f?")?;
+    if let (Some(mass_cap), Some(time_cap)) = (
+        convert_mass_re.captures(&content),
+        convert_time_re.captures(&content),
+    ) {
+        let mass = mass_cap[1].parse::<f64>().unwrap_or(0.0);
+        let time = time_cap[1].parse::<f64>().unwrap_or(0.0);
+
+        if time > 0.0 {
+            let rate = mass / time;
+
+            if let Some(in_cap) = INPUT_ELEMENT_RE.captures(&content) {
+                let element = in_cap[1].to_string();
+                if !building.inputs.iter().any(|(e, _)| e == &element) {
+                    building.inputs.push((element, rate));
+                }
+            }
+
+            if let Some(out_cap) = OUTPUT_ELEMENT_RE.captures(&content) {
+                let element = out_cap[1].to_string();
+                if !building.outputs.iter().any(|(e, _)| e == &element) {
+                    building.outputs.push((element, rate));
+                }
+            }
+
+            // Byproduct (CO2) is emitted per batch alongside the primary output
+            let byproduct_re = Regex::new(r"byproductElement\s*=\s*SimHashes\.(\w+)")?;
+            let byproduct_mass_re = Regex::new(r"byproductMass\s*=\s*([\d.]+)f?")?;
+            if let (Some(by_cap), Some(by_mass_cap)) = (
+                byproduct_re.captures(&content),
+                byproduct_mass_re.captures(&content),
+            ) {
+                let element = by_cap[1].to_string();
+                let by_rate = by_mass_cap[1].parse::<f64>().unwrap_or(0.0) / time;
+                if by_rate > 0.0 && !building.outputs.iter().any(|(e, _)| e == &element) {
+                    building.outputs.push((element, by_rate));
+                }
+            }
+        }
+    }
+
+    // Timed buildings (Sublimation Station, fabricators) produce a batch per work session
+    // Pattern: workTime = 20f; outputElement = SimHashes.ContaminatedOxygen; outputMass = 10f
+    // Rates use the base work time; skilled duplicants finish sooner
+    if let Some(cap) = WORK_TIME_RE.captures(&content) {
+        let work_time = cap[1].parse::<f64>().unwrap_or(0.0);
+
+        let output_mass_re = Regex::new(r"outputMass\s*=\s*([\d.]+)f?")?;
+        if work_time > 0.0
+            && let (Some(out_cap), Some(mass_cap)) = (
+                OUTPUT_ELEMENT_RE.captures(&content),
+                output_mass_re.captures(&content),
+            )
+        {
+            let element = out_cap[1].to_string();
+            let rate = mass_cap[1].parse::<f64>().unwrap_or(0.0) / work_time;
+            if !building.outputs.iter().any(|(e, _)| e == &element) {
+                building.outputs.push((element, rate));
+            }
+
+            // Pattern: inputElement = SimHashes.ToxicSand; inputMass = 10f
+            let input_mass_re = Regex::new(r"inputMass\s*=\s*([\d.]+)f?")?;
+            if let (Some(in_cap), Some(in_mass_cap)) = (
+                INPUT_ELEMENT_RE.captures(&content),
+                input_mass_re.captures(&content),
+            ) {
+                let element = in_cap[1].to_string();
+                let rate = in_mass_cap[1].parse::<f64>().unwrap_or(0.0) / work_time;
+                if !building.inputs.iter().any(|(e, _)| e == &element) {
+                    building.inputs.push((element, rate));
+                }
+            }
+        }
+    }
+
+    // Heat exchangers (AirConditioner, LiquidConditioner, LiquidCooledFan) move heat between fluids
+    // Pattern: temperatureDelta = -14f; pumpKGRate = 1f
+    // The piped fluid leaves as it came in, only cooler, so just the heat removed is recorded
+    let temp_delta_re = Regex::new(r"temperatureDelta\s*=\s*(-?[\d.]+)f?")?;
+    if let Some(cap) = temp_delta_re.captures(&content) {
+        let delta_c = cap[1].parse::<f64>().unwrap_or(0.0);
+
+        let (fluid, specific_heat) = if content.contains("ConduitType.Liquid") {
+            ("Liquid", WATER_SPECIFIC_HEAT)
+        } else {
+            ("Gas", OXYGEN_SPECIFIC_HEAT)
+        };
+
+        let pump_rate_re = Regex::new(r"pumpKGRate\s*=\s*([\d.]+)f?")?;
+        let rate = match pump_rate_re.captures(&content) {
+            Some(rate_cap) => Some(rate_cap[1].parse::<f64>().unwrap_or(0.0)),
+            None => building
+                .inputs
+                .iter()
+                .find(|(e, _)| e == fluid)
+                .map(|(_, r)| *r),
+        };
+        // Drop the generic conduit input; the fluid isn't consumed
+        building.inputs.retain(|(e, _)| e != fluid);
+
+        if let Some(rate) = rate {
+            // DTU/s = kg/s * 1000 g/kg * SHC (DTU/g/°C) * ΔT
+            building.heat_dtu -= rate * 1000.0 * specific_heat * delta_c.abs();
+        }
+    }
+
+    // Rocket engines and boosters burn fuel for thrust, which isn't a kg/s resource
+    // Pattern: solidBooster.efficiency = 30f; solidBooster.fuelTag = ElementLoader.FindElementByHash(SimHashes.Iron).tag;
+    let engine_re = Regex::new(r"\b(?:SolidBooster|RocketEngine(?:Cluster)?)\b")?;
+    if engine_re.is_match(&content) {
+        let thrust_re = Regex::new(r"\b(?:thrust|efficiency)\s*=\s*([\d.]+)f?")?;
+        if let Some(cap) = thrust_re.captures(&content) {
+            building.thrust_n = cap[1].parse().ok();
+        }
+
+        let fuel_re =
+            Regex::new(r"fuelTag\s*=\s*(?:ElementLoader\.FindElementByHash\()?SimHashes\.(\w+)")?;
+        let fuel_rate_re = Regex::new(r"fuelConsumptionRate\s*=\s*([\d.]+)f?")?;
+        if let (Some(fuel), Some(rate)) =
+            (fuel_re.captures(&content), fuel_rate_re.captures(&content))
+        {
+            let element = fuel[1].to_string();
+            if !building.inputs.iter().any(|(e, _)| e == &element) {
+                building
+                    .inputs
+                    .push((element, rate[1].parse::<f64>().unwrap_or(0.0)));
+            }
+        }
+    }
+
+    // Telescopes and space science buildings make research points rather than a kg/s resource
+    // Pattern: discoveryPointsPerCycle = 12.5f
+    let research_re = Regex::new(r"discoveryPointsPerCycle\s*=\s*([\d.]+)f?")?;
+    if let Some(cap) = research_re.captures(&content) {
+        building.research_points = cap[1].parse().ok();
+        building.category = Some("Science".to_string());
+    }
+
+    // A workable component means a duplicant has to operate the building, timed or not;
+    // a work time alone doesn't, and fabricators can opt out with duplicantOperated = false
+    // Pattern: go.AddOrGet<ComplexFabricatorWorkable>() or gameObject.AddComponent<WorkChore>()
+    let workable_re = Regex::new(r"\bAdd(?:OrGet|Component)<(\w*Workable|WorkChore)\b")?;
+    let unoperated_re = Regex::new(r"\bduplicantOperated\s*=\s*false\b")?;
+    building.dupe_operated = workable_re
+        .captures_iter(&content)
+        .any(|cap| &cap[1] != "DropAllWorkable") // Emptying storage, not operating
+        && !unoperated_re.is_match(&content);
+
+    // Passive thermal buildings (Insulated Tile, Tempshift Plate) only change how heat moves
+    // Pattern: thermalMass = 100f or heatConductivity = 0.01f
+    let thermal_mass_re = Regex::new(r"\bthermalMass\s*=\s*([\d.]+)f?")?;
+    if let Some(cap) = thermal_mass_re.captures(&content) {
+        building.thermal_mass = cap[1].parse().ok();
+    }
+    let conductivity_re = Regex::new(r"\bheatConductivity\s*=\s*([\d.]+)f?")?;
+    if let Some(cap) = conductivity_re.captures(&content) {
+        building.heat_conductivity = cap[1].parse().ok();
+    }
+    building.passive_thermal = (building.thermal_mass.is_some()
+        || building.heat_conductivity.is_some())
+        && building.inputs.is_empty()
+        && building.outputs.is_empty()
+        && building.recipes.is_empty();
+
+    // Extractors (Oil Well) draw from the map: outputs with nothing consumed
+    building.extractor =
+        !building.outputs.is_empty() && building.inputs.is_empty() && building.recipes.is_empty();
+
     Ok(Some(building))
 }
 
+/// Fill in a critter's ranching category and diet conversion
+///
+/// Uses the first `Diet.Info` entry: the first consumed element at
+/// `KG_*_EATEN_PER_CYCLE`, and the produced element at that rate scaled by the
+/// diet's `CONVERSION_EFFICIENCY`.
+fn parse_critter_diet(content: &str, building: &mut ExtractedBuilding) -> Result<()> {
+    building.category = Some("Ranching".to_string());
+
+    // Pattern: new Diet.Info(new HashSet<Tag> { SimHashes.Algae.CreateTag() }, SimHashes.Phosphorite.CreateTag(), ...,
+    //          TUNING.CREATURES.CONVERSION_EFFICIENCY.NORMAL, ...)
+    let diet_re = Regex::new(
+        r"new\s+Diet\.Info\s*\((?:\s*new\s+HashSet<Tag>\s*\{([^}]*)\}|[^,]*)\s*,\s*(?:SimHashes\.)?(\w+)(?:\.CreateTag\(\))?([^;]*)",
+    )?;
+    let Some(diet) = diet_re.captures(content) else {
+        return Ok(());
+    };
+
+    // Pattern: private const float KG_ALGAE_EATEN_PER_CYCLE = 7.5f;
+    let eaten_re = Regex::new(r"KG_\w*EATEN_PER_CYCLE\s*=\s*([\d.]+)f?")?;
+    let Some(eaten_kg) = eaten_re
+        .captures(content)
+        .and_then(|cap| cap[1].parse::<f64>().ok())
+    else {
+        return Ok(());
+    };
+    let eaten_rate = eaten_kg / CYCLE_LENGTH_S;
+
+    let consumed_re = Regex::new(r#"SimHashes\.(\w+)|"(\w+)"|(\w+)Config\.ID"#)?;
+    let consumed = diet
+        .get(1)
+        .and_then(|set| consumed_re.captures(set.as_str()))
+        .and_then(|cap| {
+            cap.iter()
+                .skip(1)
+                .flatten()
+                .next()
+                .map(|m| m.as_str().to_string())
+        });
+    if let Some(element) = consumed {
+        building.inputs.push((element, eaten_rate));
+    }
+
+    let efficiency_re = Regex::new(r"CONVERSION_EFFICIENCY\.(\w+)")?;
+    let efficiency = match efficiency_re.captures(&diet[3]).as_ref().map(|cap| &cap[1]) {
+        Some("BAD_2") => 0.1,
+        Some("BAD_1") => 0.25,
+        Some("GOOD_1") => 0.75,
+        Some("GOOD_2") => 0.95,
+        Some("GOOD_3") => 1.0,
+        _ => 0.5, // NORMAL
+    };
+    building
+        .outputs
+        .push((diet[2].to_string(), eaten_rate * efficiency));
+
+    Ok(())
+}
+
+/// Fill in a crop's farming category, harvest and fertilizer
+///
+/// The crop is produced at `harvestAmount / plantingTime`, and the fertilizer
+/// from `fertilizationDetails` (kg per crop) is consumed over the same time.
+/// Wild plants have no fertilizer.
+fn parse_plant(content: &str, building: &mut ExtractedBuilding) -> Result<()> {
+    building.category = Some("Farming".to_string());
+
+    // Pattern: harvestAmount = 1f; ... plantingTime = 1800f;
+    let harvest_re = Regex::new(r"harvestAmount\s*=\s*([\d.]+)f?")?;
+    let planting_re = Regex::new(r"plantingTime\s*=\s*([\d.]+)f?")?;
+    let parse = |re: &Regex| {
+        re.captures(content)
+            .and_then(|cap| cap[1].parse::<f64>().ok())
+    };
+    let (Some(harvest_amount), Some(planting_time)) = (parse(&harvest_re), parse(&planting_re))
+    else {
+        return Ok(());
+    };
+    if planting_time <= 0.0 {
+        return Ok(());
+    }
+
+    // Pattern: cropId = "BasicPlantFood" or new Crop.CropVal("BasicPlantFood", ...)
+    let crop_re = Regex::new(r#"(?:cropId\s*=\s*|Crop\.CropVal\s*\(\s*)"(\w+)""#)?;
+    if let Some(cap) = crop_re.captures(content) {
+        building
+            .outputs
+            .push((cap[1].to_string(), harvest_amount / planting_time));
+    }
+
+    // Pattern: fertilizationDetails = new FertilizationDetails(SimHashes.Dirt, 10f)
+    let fertilizer_re = Regex::new(
+        r#"fertilizationDetails\s*=\s*new\s+\w+\s*\(\s*(?:SimHashes\.|GameTags\.)?"?(\w+)"?\s*,\s*([\d.]+)f?"#,
+    )?;
+    if let Some(cap) = fertilizer_re.captures(content) {
+        let kg_per_crop = cap[2].parse::<f64>().unwrap_or(0.0);
+        building.fertilizer_kg_per_crop = Some(kg_per_crop);
+        building
+            .inputs
+            .push((cap[1].to_string(), kg_per_crop / planting_time));
+    }
+
+    Ok(())
+}
+
+/// Parse element definitions from a game `elements/*.xml` file
+///
+/// Pattern: `<Element id="Water" state="Liquid" specificHeatCapacity="4.179"
+/// thermalConductivity="0.609" lowTemp="273.15" highTemp="373.15" />`
+///
+/// `lowTemp`/`highTemp` are the Kelvin phase-transition temperatures; which of
+/// them is the melt or boil point depends on the element's state.
+pub fn parse_elements_xml(path: &Path) -> Result<Vec<Resource>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut reader = Reader::from_str(&content);
+    let mut resources = Vec::new();
+
+    loop {
+        let element = match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == "Element" => e,
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let mut attrs = HashMap::new();
+        for attr in element.attributes() {
+            let attr = attr?;
+            let key = attr.key.local_name().as_ref().to_string();
+            let value = attr.normalized_value(XmlVersion::Implicit1_0)?.to_string();
+            attrs.insert(key, value);
+        }
+
+        let Some(id) = attrs.get("id").map(|id| normalize_element_id(id)) else {
+            continue;
+        };
+        let number = |key: &str| {
+            attrs
+                .get(key)
+                .and_then(|v| v.trim_end_matches('f').parse::<f64>().ok())
+        };
+        let celsius = |key: &str| number(key).map(|k| k - 273.15);
+
+        let state = attrs.get("state").cloned();
+        let (melt_point_c, boil_point_c) = match state.as_deref() {
+            Some("Solid") => (celsius("highTemp"), None),
+            Some("Liquid") => (celsius("lowTemp"), celsius("highTemp")),
+            Some("Gas") => (None, celsius("lowTemp")),
+            _ => (None, None),
+        };
+
+        resources.push(Resource {
+            name: attrs.get("name").cloned().unwrap_or_else(|| id.clone()),
+            id,
+            state,
+            specific_heat_capacity: number("specificHeatCapacity"),
+            thermal_conductivity: number("thermalConductivity"),
+            melt_point_c,
+            boil_point_c,
+        });
+    }
+
+    Ok(resources)
+}
+
+/// Extract element properties from an XML file, or every `*.xml` file under a directory
+pub fn extract_elements_to_database(conn: &Connection, path: &Path) -> Result<usize> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        WalkDir::new(path)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "xml"))
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut count = 0;
+    for filepath in &files {
+        match parse_elements_xml(filepath) {
+            Ok(resources) => {
+                for resource in &resources {
+                    db::upsert_resource(conn, resource)?;
+                }
+                println!(
+                    "  Parsed: {} ({} elements)",
+                    filepath.display(),
+                    resources.len()
+                );
+                count += resources.len();
+            }
+            Err(e) => eprintln!("  Error parsing {}: {}", filepath.display(), e),
+        }
+    }
+
+    Ok(count)
+}
+
 /// Extract all building data from decompiled source and populate database
 pub fn extract_to_database(conn: &Connection, decompiled_dir: &Path) -> Result<ExtractStats> {
     let mut stats = ExtractStats::default();
 
-    println!("Scanning {} for building configs...", decompiled_dir.display());
+    println!(
+        "Scanning {} for building configs...",
+        decompiled_dir.display()
+    );
     let config_files = find_config_files(decompiled_dir)?;
-    println!("Found {} potential building config files", config_files.len());
+    println!(
+        "Found {} potential building config files",
+        config_files.len()
+    );
+
+    stats.game_version = detect_game_version(decompiled_dir)?;
+    if let Some(version) = &stats.game_version {
+        db::set_metadata(conn, db::METADATA_GAME_VERSION, version)?;
+    }
 
-    for filepath in &config_files {
-        match parse_building_config(filepath) {
+    let mut descriptions = HashMap::new();
+    for filepath in find_strings_files(decompiled_dir) {
+        match parse_building_strings(&filepath) {
+            Ok(parsed) => descriptions.extend(parsed),
+            Err(e) => eprintln!("  Error parsing {}: {}", filepath.display(), e),
+        }
+    }
+
+    for (filepath, dlc) in &config_files {
+        let started = Instant::now();
+        let parsed = parse_building_config(filepath, dlc.as_deref());
+        stats
+            .per_file_timings
+            .push((filepath.clone(), started.elapsed()));
+        match parsed {
             Ok(Some(extracted)) => {
                 // Create building record
                 let building = Building {
                     id: extracted.id.clone(),
                     name: extracted.id.clone(), // Use ID as name for now
-                    category: None,
+                    category: extracted.category.clone(),
                     power_watts: extracted.power_watts,
                     heat_output_dtu: extracted.heat_dtu,
                     construction_time_s: None,
+                    dlc: extracted.dlc.clone(),
+                    building_type: extracted.building_type,
+                    min_critters: extracted.min_critters,
+                    extractor: extracted.extractor,
+                    dupe_operated: extracted.dupe_operated,
+                    passive_thermal: extracted.passive_thermal,
+                    thermal_mass: extracted.thermal_mass,
+                    heat_conductivity: extracted.heat_conductivity,
                 };
 
                 db::upsert_building(conn, &building)?;
 
+                if let Some(description) = descriptions.get(&extracted.id.to_uppercase()) {
+                    db::upsert_building_description(conn, &extracted.id, description)?;
+                    stats.descriptions += 1;
+                }
+
                 // Insert inputs
                 for (element, rate) in &extracted.inputs {
                     let input = BuildingInput {
                         building_id: extracted.id.clone(),
-                        resource_id: element.clone(),
+                        resource_id: normalize_element_id(element),
                         rate_kg_per_s: *rate,
                     };
                     db::insert_building_input(conn, &input)?;
@@ -275,22 +1094,65 @@ pub fn extract_to_database(conn: &Connection, decompiled_dir: &Path) -> Result<E
                 for (element, rate) in &extracted.outputs {
                     let output = BuildingOutput {
                         building_id: extracted.id.clone(),
-                        resource_id: element.clone(),
+                        resource_id: normalize_element_id(element),
                         rate_kg_per_s: *rate,
+                        output_type: BuildingOutputType::Mass,
+                    };
+                    db::insert_building_output(conn, &output)?;
+                }
+                if let Some(thrust) = extracted.thrust_n {
+                    let output = BuildingOutput {
+                        building_id: extracted.id.clone(),
+                        resource_id: "Thrust".to_string(),
+                        rate_kg_per_s: thrust,
+                        output_type: BuildingOutputType::Thrust,
+                    };
+                    db::insert_building_output(conn, &output)?;
+                }
+                if let Some(points) = extracted.research_points {
+                    let output = BuildingOutput {
+                        building_id: extracted.id.clone(),
+                        resource_id: "ResearchPoints".to_string(),
+                        rate_kg_per_s: points,
+                        output_type: BuildingOutputType::Research,
                     };
                     db::insert_building_output(conn, &output)?;
                 }
 
+                for recipe in &extracted.recipes {
+                    let normalize = |flows: &Vec<(String, f64)>| -> Vec<(String, f64)> {
+                        flows
+                            .iter()
+                            .map(|(e, rate)| (normalize_element_id(e), *rate))
+                            .collect()
+                    };
+                    db::upsert_recipe(
+                        conn,
+                        &extracted.id,
+                        &recipe.name,
+                        &normalize(&recipe.inputs),
+                        &normalize(&recipe.outputs),
+                        Some(recipe.cycle_time_s),
+                        recipe.recipe_type.as_deref(),
+                    )?;
+                }
+                stats.recipes += extracted.recipes.len();
+
                 stats.buildings += 1;
                 stats.inputs += extracted.inputs.len();
                 stats.outputs += extracted.outputs.len();
 
+                let fertilizer = extracted
+                    .fertilizer_kg_per_crop
+                    .map(|kg| format!(", fertilizer: {} kg/crop", kg))
+                    .unwrap_or_default();
                 println!(
-                    "  Parsed: {} (power: {}W, inputs: {}, outputs: {})",
+                    "  Parsed: {} (power: {}W, inputs: {}, outputs: {}{})",
                     extracted.id,
                     extracted.power_watts,
                     extracted.inputs.len(),
-                    extracted.outputs.len()
+                    extracted.outputs.len(),
+                    fertilizer
                 );
             }
             Ok(None) => {
@@ -304,6 +1166,40 @@ pub fn extract_to_database(conn: &Connection, decompiled_dir: &Path) -> Result<E
         }
     }
 
+    for filepath in find_food_configs(decompiled_dir) {
+        let content = fs::read_to_string(&filepath).unwrap_or_default();
+        match parse_food_info(&content) {
+            Ok(Some((food, calories))) => {
+                db::upsert_food_value(conn, &food, Some(calories))?;
+                stats.foods += 1;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("  Error parsing {}: {}", filepath.display(), e),
+        }
+    }
+    db::update_recipe_calories(conn)?;
+
+    // Element properties, when the game's elements XML was exported alongside the source
+    for filepath in find_element_files(decompiled_dir) {
+        match parse_elements_xml(&filepath) {
+            Ok(resources) => {
+                for resource in &resources {
+                    db::upsert_resource(conn, resource)?;
+                }
+                stats.resources += resources.len();
+            }
+            Err(e) => eprintln!("  Error parsing {}: {}", filepath.display(), e),
+        }
+    }
+
+    let missing_io = db::list_buildings_missing_io(conn)?;
+    if missing_io.len() > 10 {
+        eprintln!(
+            "Warning: {} buildings have no inputs, outputs or recipes; run 'validate' to list them",
+            missing_io.len()
+        );
+    }
+
     Ok(stats)
 }
 
@@ -312,16 +1208,329 @@ pub struct ExtractStats {
     pub buildings: usize,
     pub inputs: usize,
     pub outputs: usize,
+    pub descriptions: usize,
+    pub recipes: usize,
+    pub foods: usize,
+    pub resources: usize,
     pub skipped: usize,
     pub errors: usize,
+    pub game_version: Option<String>,
+    pub per_file_timings: Vec<(PathBuf, Duration)>, // Time spent in parse_building_config per file
+}
+
+impl ExtractStats {
+    /// The `n` config files that took longest to parse, slowest first
+    pub fn slowest_files(&self, n: usize) -> Vec<&(PathBuf, Duration)> {
+        let mut timings: Vec<_> = self.per_file_timings.iter().collect();
+        timings.sort_by_key(|(_, elapsed)| std::cmp::Reverse(*elapsed));
+        timings.truncate(n);
+        timings
+    }
 }
 
 impl std::fmt::Display for ExtractStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Extracted {} buildings ({} inputs, {} outputs). Skipped: {}, Errors: {}",
-            self.buildings, self.inputs, self.outputs, self.skipped, self.errors
-        )
+            "Extracted {} buildings ({} inputs, {} outputs, {} recipes, {} descriptions), {} foods, {} resources. \
+             Skipped: {}, Errors: {}",
+            self.buildings,
+            self.inputs,
+            self.outputs,
+            self.recipes,
+            self.descriptions,
+            self.foods,
+            self.resources,
+            self.skipped,
+            self.errors
+        )?;
+        if let Some(version) = &self.game_version {
+            write!(f, "\nGame version: {}", version)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `source` to a temporary `{name}.cs` and parse it as a building config
+    fn parse_snippet(name: &str, source: &str) -> ExtractedBuilding {
+        let path = std::env::temp_dir().join(format!(
+            "oni-calculator-test-{}-{}.cs",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, source).unwrap();
+        let parsed = parse_building_config(&path, None);
+        fs::remove_file(&path).unwrap();
+        parsed.unwrap().expect("snippet should parse as a building")
+    }
+
+    fn rate_of(flows: &[(String, f64)], element: &str) -> Option<f64> {
+        flows
+            .iter()
+            .find(|(e, _)| e == element)
+            .map(|(_, rate)| *rate)
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("element should be present");
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn normalize_element_id_cases() {
+        let cases = [
+            ("Water", "Water"),
+            ("water", "Water"),
+            ("  Water  ", "Water"),
+            ("\tOxygen\n", "Oxygen"),
+            ("CarbonDioxide", "CarbonDioxide"),
+            ("WaterElement", "Water"),
+            ("OxygenTag", "Oxygen"),
+            ("waterTag", "Water"),
+            (" dirtElement ", "Dirt"),
+            ("ElementTag", "Element"),
+            ("TagElement", "Tag"),
+            ("XTag", "X"),
+            ("Tag", "Tag"),
+            ("Element", "Element"),
+            ("tag", "Tag"),
+            ("element", "Element"),
+            ("", ""),
+            ("   ", ""),
+            ("éther", "Éther"),
+            ("ßand", "SSand"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(normalize_element_id(raw), expected, "normalizing {:?}", raw);
+        }
+    }
+
+    #[test]
+    fn distillery_ethanol_and_byproduct_rates() {
+        let building = parse_snippet(
+            "distillery",
+            r#"public class EthanolDistilleryConfig : IBuildingConfig
+{
+    public const string ID = "EthanolDistillery";
+    public override BuildingDef CreateBuildingDef()
+    {
+        BuildingDef obj = BuildingTemplates.CreateBuildingDef(ID, 4, 3, "kanim", 30, 30f, null, null, 800f);
+        obj.EnergyConsumptionWhenActive = 240f;
+        return obj;
+    }
+    public override void ConfigureBuildingTemplate(GameObject go, Tag prefab_tag)
+    {
+        Fermenter fermenter = go.AddOrGet<Fermenter>();
+        fermenter.inputElement = SimHashes.WoodLog;
+        fermenter.convertMass = 1f;
+        fermenter.ConvertTime = 2f;
+        fermenter.outputElement = SimHashes.Ethanol;
+        fermenter.byproductElement = SimHashes.CarbonDioxide;
+        fermenter.byproductMass = 0.33f;
+    }
+}"#,
+        );
+
+        assert_eq!(building.id, "EthanolDistillery");
+        assert_close(rate_of(&building.inputs, "WoodLog"), 0.5);
+        assert_close(rate_of(&building.outputs, "Ethanol"), 0.5);
+        assert_close(rate_of(&building.outputs, "CarbonDioxide"), 0.165);
+    }
+
+    #[test]
+    fn cast_literals_from_newer_decompilers() {
+        let building = parse_snippet(
+            "casts",
+            r#"public class ElectrolyzerConfig : IBuildingConfig
+{
+    public const string ID = "Electrolyzer";
+    public override BuildingDef CreateBuildingDef()
+    {
+        BuildingDef buildingDef = BuildingTemplates.CreateBuildingDef(ID, 2, 2, "kanim", 30, 30f, null, null, 800f);
+        buildingDef.EnergyConsumptionWhenActive = (float)120;
+        buildingDef.ExhaustKilowattsWhenActive = (double)0.25;
+        buildingDef.SelfHeatKilowattsWhenActive = (float) 1;
+        return buildingDef;
+    }
+    public override void ConfigureBuildingTemplate(GameObject go, Tag prefab_tag)
+    {
+        ElementConverter elementConverter = go.AddOrGet<ElementConverter>();
+        elementConverter.consumedElements = new ElementConverter.ConsumedElement[] {
+            new ElementConverter.ConsumedElement(new Tag("Water"), (float)1, true),
+            new ElementConverter.ConsumedElement(SimHashes.Dirt, (double)0.5)
+        };
+    }
+}"#,
+        );
+
+        assert_eq!(building.power_watts, -120.0);
+        assert!(
+            (building.heat_dtu - 1250.0).abs() < 1e-9,
+            "heat was {}",
+            building.heat_dtu
+        );
+        assert_close(rate_of(&building.inputs, "Water"), 1.0);
+        assert_close(rate_of(&building.inputs, "Dirt"), 0.5);
+    }
+
+    #[test]
+    fn strip_literal_casts_leaves_other_casts() {
+        let stripped =
+            strip_literal_casts("a = (float)120; b = (double) 0.5; c = (int)3; d = (float)x;")
+                .unwrap();
+        assert_eq!(stripped, "a = 120; b = 0.5; c = (int)3; d = (float)x;");
+    }
+
+    #[test]
+    fn heat_exchanger_records_heat_without_fluid_io() {
+        let building = parse_snippet(
+            "aircon",
+            r#"public class AirConditionerConfig : IBuildingConfig
+{
+    public const string ID = "AirConditioner";
+    public override BuildingDef CreateBuildingDef()
+    {
+        BuildingDef obj = BuildingTemplates.CreateBuildingDef(ID, 2, 2, "kanim", 30, 30f, null, null, 800f);
+        obj.EnergyConsumptionWhenActive = 240f;
+        obj.InputConduitType = ConduitType.Gas;
+        return obj;
+    }
+    public override void ConfigureBuildingTemplate(GameObject go, Tag prefab_tag)
+    {
+        AirConditioner airConditioner = go.AddOrGet<AirConditioner>();
+        airConditioner.temperatureDelta = -14f;
+        ConduitConsumer conduitConsumer = go.AddOrGet<ConduitConsumer>();
+        conduitConsumer.consumptionRate = 1f;
+    }
+}"#,
+        );
+
+        assert!(building.inputs.is_empty(), "inputs: {:?}", building.inputs);
+        assert!(
+            building.outputs.is_empty(),
+            "outputs: {:?}",
+            building.outputs
+        );
+        assert!(
+            (building.heat_dtu + 1000.0 * OXYGEN_SPECIFIC_HEAT * 14.0).abs() < 1e-6,
+            "heat {}",
+            building.heat_dtu
+        );
+    }
+
+    #[test]
+    fn attribute_modifiers_keep_io() {
+        let source = |id: &str, marker: &str| {
+            format!(
+                r#"public class {id}Config : IBuildingConfig
+{{
+    public const string ID = "{id}";
+    public override BuildingDef CreateBuildingDef()
+    {{
+        BuildingDef obj = BuildingTemplates.CreateBuildingDef(ID, 2, 2, "kanim", 30, 30f, null, null, 800f);
+        obj.EnergyConsumptionWhenActive = 60f;
+        return obj;
+    }}
+    public override void ConfigureBuildingTemplate(GameObject go, Tag prefab_tag)
+    {{
+        {marker}
+        new AttributeModifier(Db.Get().Attributes.Machinery.Id, 2f, "bonus");
+        ElementConverter elementConverter = go.AddOrGet<ElementConverter>();
+        elementConverter.consumedElements = new ElementConverter.ConsumedElement[] {{
+            new ElementConverter.ConsumedElement(new Tag("Water"), 0.1f, true)
+        }};
+    }}
+}}"#
+            )
+        };
+
+        let production = parse_snippet("modifier", &source("Workbench", ""));
+        assert_eq!(production.building_type, BuildingType::Production);
+        assert_close(rate_of(&production.inputs, "Water"), 0.1);
+
+        let station = parse_snippet(
+            "skillstation",
+            &source("CraftingStation", "go.AddOrGet<SkillStation>();"),
+        );
+        assert_eq!(station.building_type, BuildingType::Services);
+        assert_eq!(station.category.as_deref(), Some("Skills"));
+        assert_eq!(station.power_watts, -60.0);
+        assert_close(rate_of(&station.inputs, "Water"), 0.1);
+    }
+
+    #[test]
+    fn thermal_values_are_captured() {
+        let tile = parse_snippet(
+            "insulatedtile",
+            r#"public class InsulationTileConfig : IBuildingConfig
+{
+    public const string ID = "InsulationTile";
+    public override BuildingDef CreateBuildingDef()
+    {
+        BuildingDef obj = BuildingTemplates.CreateBuildingDef(ID, 1, 1, "kanim", 100, 30f, null, null, 800f);
+        obj.ThermalConductivity = 0.01f;
+        return obj;
+    }
+    public override void ConfigureBuildingTemplate(GameObject go, Tag prefab_tag)
+    {
+        go.AddOrGet<PrimaryElement>().thermalMass = 100f;
+        go.AddOrGet<SimCellOccupier>().heatConductivity = 0.01f;
+    }
+}"#,
+        );
+        assert!(tile.passive_thermal);
+        assert_eq!(tile.thermal_mass, Some(100.0));
+        assert_eq!(tile.heat_conductivity, Some(0.01));
+    }
+
+    #[test]
+    fn work_time_alone_is_not_dupe_operated() {
+        let source = |name: &str, body: &str| {
+            format!(
+                r#"public class {name}Config : IBuildingConfig
+{{
+    public const string ID = "{name}";
+    public override BuildingDef CreateBuildingDef()
+    {{
+        BuildingDef obj = BuildingTemplates.CreateBuildingDef(ID, 3, 3, "kanim", 30, 30f, null, null, 800f);
+        obj.EnergyConsumptionWhenActive = 240f;
+        return obj;
+    }}
+    public override void ConfigureBuildingTemplate(GameObject go, Tag prefab_tag)
+    {{
+        go.AddOrGet<DropAllWorkable>();
+        {body}
+        fabricator.workTime = 40f;
+    }}
+}}"#
+            )
+        };
+
+        let automatic = parse_snippet("automatic", &source("Kiln", ""));
+        assert!(!automatic.dupe_operated);
+
+        let opted_out = parse_snippet(
+            "optedout",
+            &source(
+                "Smelter",
+                "go.AddOrGet<ComplexFabricatorWorkable>(); fabricator.duplicantOperated = false;",
+            ),
+        );
+        assert!(!opted_out.dupe_operated);
+
+        let operated = parse_snippet(
+            "operated",
+            &source("MicrobeMusher", "go.AddOrGet<ComplexFabricatorWorkable>();"),
+        );
+        assert!(operated.dupe_operated);
     }
 }
@@ -0,0 +1,136 @@
+//! Graphviz DOT rendering of the building/resource graph
+//!
+//! Buildings are drawn as boxes and resources as ellipses; edges run from
+//! each input resource into its building and from the building to its outputs.
+
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+
+/// Graphviz engine named in the graph's `layout` attribute
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Layout {
+    /// Hierarchical tree
+    Dot,
+    /// Spring-model layered layout
+    Neato,
+    /// Circular layout
+    Circo,
+}
+
+impl Layout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layout::Dot => "dot",
+            Layout::Neato => "neato",
+            Layout::Circo => "circo",
+        }
+    }
+}
+
+/// Presentation options shared by the DOT-emitting commands
+#[derive(Debug, Clone, clap::Args)]
+pub struct DotStyle {
+    /// Graphviz layout engine
+    #[arg(long, value_enum, default_value_t = Layout::Dot)]
+    pub layout: Layout,
+
+    /// Fill a building with a color, as BUILDING=COLOR (repeatable, e.g. Electrolyzer=lightblue)
+    #[arg(long = "node-color", value_parser = parse_node_color)]
+    pub node_colors: Vec<(String, String)>,
+}
+
+/// Parse a `BUILDING=COLOR` pair for `--node-color`
+fn parse_node_color(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((building, color)) if !building.is_empty() && !color.is_empty() => {
+            Ok((building.to_string(), color.to_string()))
+        }
+        _ => Err(format!("expected BUILDING=COLOR, got '{}'", s)),
+    }
+}
+
+/// `(building_id, resource_id, direction, rate)` as returned by `db::list_all_edges`
+pub type Edge = (String, String, String, f64);
+
+/// Edges within `depth` building hops of `building`
+///
+/// Hop 1 covers the building's own inputs and outputs plus the buildings
+/// producing those inputs or consuming those outputs (through those resources
+/// only); each further hop repeats this from the buildings just added.
+pub fn neighborhood<'a>(edges: &'a [Edge], building: &str, depth: usize) -> Vec<&'a Edge> {
+    let mut buildings: HashSet<&str> = HashSet::from([building]);
+    let mut resources: HashSet<&str> = HashSet::new();
+    let mut frontier: HashSet<&str> = HashSet::from([building]);
+
+    for _ in 0..depth {
+        let new_resources: HashSet<&str> = edges
+            .iter()
+            .filter(|(b, r, _, _)| frontier.contains(b.as_str()) && !resources.contains(r.as_str()))
+            .map(|(_, r, _, _)| r.as_str())
+            .collect();
+        frontier = edges
+            .iter()
+            .filter(|(b, r, _, _)| {
+                new_resources.contains(r.as_str()) && !buildings.contains(b.as_str())
+            })
+            .map(|(b, _, _, _)| b.as_str())
+            .collect();
+        resources.extend(new_resources);
+        buildings.extend(&frontier);
+    }
+
+    edges
+        .iter()
+        .filter(|(b, r, _, _)| buildings.contains(b.as_str()) && resources.contains(r.as_str()))
+        .collect()
+}
+
+/// Render edges as a DOT digraph, filling the `highlight` building and any colored in `style`
+pub fn to_dot(edges: &[&Edge], highlight: Option<&str>, style: &DotStyle) -> String {
+    let mut output = format!(
+        "digraph oni {{\n    layout={};\n    rankdir=LR;\n",
+        style.layout.as_str()
+    );
+
+    // Node IDs are prefixed so a building and a resource may share a name
+    let mut declared = HashSet::new();
+    for (building, resource, _, _) in edges {
+        if declared.insert(format!("b:{}", building)) {
+            let color = style
+                .node_colors
+                .iter()
+                .find(|(b, _)| b == building)
+                .map(|(_, c)| c);
+            let fill = match color {
+                Some(color) => format!(", style=filled, fillcolor=\"{}\"", color),
+                None if highlight == Some(building.as_str()) => ", style=filled".to_string(),
+                None => String::new(),
+            };
+            output.push_str(&format!(
+                "    \"b:{0}\" [label=\"{0}\", shape=box{1}];\n",
+                building, fill
+            ));
+        }
+        if declared.insert(format!("r:{}", resource)) {
+            output.push_str(&format!(
+                "    \"r:{0}\" [label=\"{0}\", shape=ellipse];\n",
+                resource
+            ));
+        }
+    }
+
+    for (building, resource, direction, rate) in edges {
+        let (from, to) = match direction.as_str() {
+            "input" => (format!("r:{}", resource), format!("b:{}", building)),
+            _ => (format!("b:{}", building), format!("r:{}", resource)),
+        };
+        output.push_str(&format!(
+            "    \"{}\" -> \"{}\" [label=\"{:.3}\"];\n",
+            from, to, rate
+        ));
+    }
+
+    output.push_str("}\n");
+    output
+}
@@ -0,0 +1,146 @@
+//! Interactive shell with tab completion
+//!
+//! Each line is parsed as a subcommand (e.g. `calc Oxygen --rate 2`) and run
+//! against the already-open database.
+
+use std::path::Path;
+
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+use rusqlite::Connection;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::{Commands, db};
+
+/// A single line of interactive input
+#[derive(Parser)]
+#[command(name = "oni", no_binary_name = true)]
+struct ReplLine {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// Completes subcommand names, building IDs and resource IDs
+struct OniHelper {
+    candidates: Vec<String>,
+}
+
+impl OniHelper {
+    fn load(conn: &Connection) -> Result<Self> {
+        let mut candidates: Vec<String> = ReplLine::command()
+            .get_subcommands()
+            .map(|c| c.get_name().to_string())
+            .chain(["help".to_string(), "exit".to_string()])
+            .collect();
+        candidates.extend(db::list_buildings(conn)?.into_iter().map(|b| b.id));
+        candidates.extend(db::list_resource_ids(conn)?);
+        candidates.sort();
+        candidates.dedup();
+        Ok(Self { candidates })
+    }
+}
+
+impl Completer for OniHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = line[start..pos].to_lowercase();
+        let matches = self
+            .candidates
+            .iter()
+            .filter(|c| c.to_lowercase().starts_with(&word))
+            .cloned()
+            .collect();
+        Ok((start, matches))
+    }
+}
+
+impl Hinter for OniHelper {
+    type Hint = String;
+}
+
+impl Highlighter for OniHelper {}
+
+impl Validator for OniHelper {}
+
+impl Helper for OniHelper {}
+
+/// Run the interactive shell until `exit` or EOF
+pub fn run(conn: &Connection, database: &Path) -> Result<()> {
+    let mut editor = Editor::new()?;
+    editor.set_helper(Some(OniHelper::load(conn)?));
+
+    println!("ONI calculator interactive mode. Type 'help' for commands, 'exit' to quit.");
+
+    loop {
+        let line = match editor.readline("oni> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let args = split_args(&line);
+        if args.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line.as_str())?;
+
+        match args[0].as_str() {
+            "exit" | "quit" => break,
+            "help" => {
+                // Same text as `<command> --help`
+                let help_args = match args.get(1) {
+                    Some(command) => vec![command.as_str(), "--help"],
+                    None => vec!["--help"],
+                };
+                if let Err(e) = ReplLine::try_parse_from(help_args) {
+                    e.print()?;
+                }
+            }
+            _ => match ReplLine::try_parse_from(&args) {
+                Ok(parsed) => {
+                    if let Err(e) = crate::run_command(conn, database, parsed.command) {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+                Err(e) => e.print()?,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Split a line into arguments, keeping double-quoted text together
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
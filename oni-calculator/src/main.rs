@@ -2,16 +2,32 @@
 //!
 //! A production chain calculator for Oxygen Not Included.
 
+mod calc_file;
 mod calculator;
 mod db;
 mod extract;
+mod graph;
+mod interactive;
 mod models;
+mod sanity;
+mod scenario_file;
+mod update_check;
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use rusqlite::Connection;
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand, ValueEnum};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::models::{
+    Building, BuildingFilter, BuildingOutputType, HypotheticalField, HypotheticalOverride,
+    MergeConflictMode, ProducerOrder, ProductionChainError, ProductionForest, ProductionNodeDiff,
+    ProductionTree, RateOverride,
+};
 
 #[derive(Parser)]
 #[command(name = "oni-calculator")]
@@ -21,6 +37,14 @@ struct Cli {
     #[arg(short, long, default_value = "oni_data.db")]
     database: PathBuf,
 
+    /// SQLite page cache size (positive = pages, negative = KiB)
+    #[arg(long, allow_hyphen_values = true)]
+    cache_size: Option<i64>,
+
+    /// Don't switch the database to WAL journal mode
+    #[arg(long)]
+    no_wal: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -35,12 +59,23 @@ enum Commands {
         /// Clear existing data before extraction
         #[arg(long)]
         clear: bool,
+
+        /// Print the 10 config files that took longest to parse
+        #[arg(long)]
+        profile: bool,
+    },
+
+    /// Extract element properties from the game's elements XML files
+    ExtractElements {
+        /// Elements XML file or directory containing them
+        path: PathBuf,
     },
 
     /// Calculate production chain for a target resource
     Calc {
         /// Target resource to produce (e.g., "Oxygen", "Steel")
-        resource: String,
+        #[arg(required_unless_present = "scenario_file")]
+        resource: Option<String>,
 
         /// Target production rate in kg/s
         #[arg(short, long, default_value = "1.0")]
@@ -49,18 +84,485 @@ enum Commands {
         /// Show detailed production tree
         #[arg(short, long)]
         verbose: bool,
+
+        /// Only use base-game buildings
+        #[arg(long)]
+        exclude_dlc: bool,
+
+        /// Round building counts up to whole buildings
+        #[arg(long)]
+        ceil: bool,
+
+        /// Print the rate actually produced by the whole buildings
+        #[arg(long, requires = "ceil")]
+        output_rate_achieved: bool,
+
+        /// Append each building's tooltip description to the production tree
+        #[arg(long, requires = "verbose")]
+        with_notes: bool,
+
+        /// Prefer producers in this building category (e.g. "Oxygen")
+        #[arg(long)]
+        prefer_category: Option<String>,
+
+        /// Print a one-line summary instead of the full summary
+        #[arg(long, conflicts_with = "verbose")]
+        compact: bool,
+
+        /// Multiply the calculated chain (counts, power, rates) by this factor
+        #[arg(long)]
+        scale: Option<f64>,
+
+        /// Build extractors (e.g. Oil Well) for upstream inputs instead of treating them as raw inputs
+        #[arg(long)]
+        include_extractors: bool,
+
+        /// Only use producers that run without a duplicant operating them
+        #[arg(long)]
+        exclude_dupe_operated: bool,
+
+        /// Producer choice: first-found, min-power, min-heat, max-output-rate, or a building ID to prefer
+        #[arg(long, default_value = "first-found", value_parser = calculator::ProducerStrategy::parse)]
+        strategy: calculator::ProducerStrategy,
+
+        /// Suggest buildings that could consume the chain's byproducts
+        #[arg(long)]
+        find_loops: bool,
+
+        /// Subtract the chain's byproducts and surplus from the raw inputs they can supply
+        #[arg(long)]
+        credit_byproducts: bool,
+
+        /// Read targets from a file of `RESOURCE RATE` lines instead
+        #[arg(long, conflicts_with = "resource")]
+        scenario_file: Option<PathBuf>,
+
+        /// Make the target with this building recipe (e.g. "Iron" on the Metal Refinery)
+        #[arg(long, conflicts_with = "scenario_file")]
+        recipe: Option<String>,
+
+        /// Warn about inputs that would freeze, melt or boil at this delivery temperature (°C)
+        #[arg(long, allow_hyphen_values = true)]
+        temperature: Option<f64>,
+
+        /// Find the highest rate whose chain consumes at most this many watts (replaces --rate)
+        #[arg(long, conflicts_with_all = ["scenario_file", "recipe"])]
+        max_power: Option<f64>,
+
+        /// Highest rate --max-power searches up to, in kg/s
+        #[arg(long, default_value = "100.0", requires = "max_power")]
+        rate_bound: f64,
+    },
+
+    /// Copy buildings from another database into this one
+    Merge {
+        /// Database to copy buildings from
+        source_db: PathBuf,
+
+        /// What to do with buildings both databases have in different versions
+        #[arg(long, value_enum, default_value_t = MergeConflictMode::Skip)]
+        conflict: MergeConflictMode,
+
+        /// JSON file `--conflict report` lists the conflicting buildings in
+        #[arg(long, default_value = "conflict_report.json")]
+        report_file: PathBuf,
+    },
+
+    /// Compare a calculation against the same calculation in another database
+    RecalcDiff {
+        /// Target resource to produce
+        resource: String,
+
+        /// Target production rate in kg/s
+        #[arg(short, long, default_value = "1.0")]
+        rate: f64,
+
+        /// Database to compare against (the "old" side)
+        compare_db: PathBuf,
+    },
+
+    /// Calculate the buildings a self-sustaining colony needs (oxygen, water, food, power)
+    CalcNetwork {
+        /// Number of duplicants
+        dupes: u32,
+
+        /// Oxygen breathed per duplicant in kg/s
+        #[arg(long, default_value = "0.1")]
+        oxygen_per_dupe: f64,
+
+        /// Water used per duplicant in kg/s
+        #[arg(long, default_value = "0")]
+        water_per_dupe: f64,
+
+        /// Calories eaten per duplicant per cycle (kcal)
+        #[arg(long, default_value = "1000")]
+        kcal_per_dupe: f64,
+
+        /// Food to produce (default: first known food with a producer)
+        #[arg(long)]
+        food: Option<String>,
+
+        /// Show each chain's production tree
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Calculate several targets at once and print their combined summary
+    CalcMultiple {
+        /// `RESOURCE=RATE` or `RESOURCE:RATE` pairs in kg/s (e.g. Oxygen=1.0 Water:0.5)
+        #[arg(required = true, value_parser = parse_target)]
+        targets: Vec<(String, f64)>,
+
+        /// Show each target's production tree
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Find the fewest cooling buildings that absorb a heat load
+    CalcHeatOnly {
+        /// Heat to remove in DTU/s
+        heat_load: f64,
+
+        /// Ambient temperature the coolers run at (°C); coolers whose inputs would change state are skipped
+        #[arg(long, default_value = "20.0", allow_hyphen_values = true)]
+        ambient_temp: f64,
+    },
+
+    /// Calculate every producible resource and write the summaries to a JSON file
+    CalcAll {
+        /// Output JSON file
+        output: PathBuf,
+
+        /// Target rate for every resource in kg/s
+        #[arg(short, long, default_value = "1.0")]
+        rate: f64,
+    },
+
+    /// Find production cycles through a resource and show which ones gain resource or power
+    Loops {
+        /// Resource the cycles start and end with (e.g. "Water")
+        resource: String,
+
+        /// Longest cycle to consider, in buildings
+        #[arg(long, default_value = "4")]
+        max_len: usize,
+    },
+
+    /// Calculate the chain for one building as if it had different stats (database untouched)
+    WhatIf {
+        /// Building ID to test
+        building: String,
+
+        /// Hypothetical power in watts (negative = consumption)
+        #[arg(long, allow_hyphen_values = true)]
+        power_watts: f64,
+
+        /// Hypothetical output rate of the building's primary output in kg/s
+        #[arg(short, long, value_parser = parse_positive_rate)]
+        rate: f64,
+    },
+
+    /// Pick the producers that minimize power, heat, building count or construction cost
+    Optimize {
+        /// Target resource to produce
+        resource: String,
+
+        /// Target production rate in kg/s
+        #[arg(short, long, default_value = "1.0")]
+        rate: f64,
+
+        /// What to minimize
+        #[arg(long, value_enum, default_value_t = calculator::OptimizeObjective::Power)]
+        objective: calculator::OptimizeObjective,
+
+        /// Maximum number of producer combinations to evaluate
+        #[arg(long, default_value = "64")]
+        max_combinations: usize,
+    },
+
+    /// Per-building power of a chain computed in SQL, checked against the calculator
+    PowerBreakdown {
+        /// Target resource to produce
+        resource: String,
+
+        /// Target production rate in kg/s
+        #[arg(short, long, default_value = "1.0")]
+        rate: f64,
+    },
+
+    /// Run a calculation saved in a JSON or TOML file
+    CalcFromFile {
+        /// Saved calculation (`.json`, otherwise TOML)
+        path: PathBuf,
     },
 
     /// List all buildings in the database
-    ListBuildings,
+    ListBuildings {
+        /// Only list base-game buildings
+        #[arg(long)]
+        exclude_dlc: bool,
+
+        /// Only list buildings a duplicant has to operate
+        #[arg(long, conflicts_with = "fully_automatic")]
+        dupe_operated: bool,
+
+        /// Only list buildings that run without a duplicant
+        #[arg(long)]
+        fully_automatic: bool,
+
+        /// Show all columns (heat output, I/O counts) instead of the summary
+        #[arg(long, visible_alias = "verbose")]
+        full: bool,
+
+        /// Sort key (heat, I/O counts and construction time imply --full)
+        #[arg(long, value_enum, default_value_t = SortBy::Name)]
+        sort_by: SortBy,
+
+        /// Sort in descending order
+        #[arg(long)]
+        desc: bool,
+    },
 
     /// List all producible resources
-    ListResources,
+    ListResources {
+        /// Only list resources of this type
+        #[arg(long, value_enum)]
+        resource_type: Option<ResourceType>,
+    },
+
+    /// List food items with their calories
+    ListFoods,
+
+    /// Show a resource's thermal properties (all resources if no ID is given)
+    Resource {
+        /// Resource ID (e.g. "Water")
+        id: Option<String>,
+    },
+
+    /// List cooking recipes by calories produced per kg of input, highest first
+    ListFoodRecipes,
+
+    /// List buildings that remove heat, most cooling first
+    ListCoolers,
 
     /// Show details for a specific building
     Building {
         /// Building ID
         id: String,
+
+        /// Draw ASCII borders around tables
+        #[arg(long)]
+        borders: bool,
+    },
+
+    /// Show the fields that differ between two buildings
+    BuildingDiff {
+        /// First building ID
+        before: String,
+
+        /// Second building ID
+        after: String,
+    },
+
+    /// Rank a building's recipes by output mass per input mass
+    RecipeEfficiency {
+        /// Building ID
+        building: String,
+    },
+
+    /// Find buildings that produce the same outputs as a given building
+    FindAlternatives {
+        /// Building ID
+        building: String,
+    },
+
+    /// List buildings that can run, directly or downstream, starting from a resource
+    Reachable {
+        /// Starting resource (e.g. "Coal")
+        resource: String,
+
+        /// Maximum number of building steps to follow
+        #[arg(long, default_value = "5")]
+        max_steps: usize,
+    },
+
+    /// List every resource a building needs, including the inputs of its upstream producers
+    TransitiveInputs {
+        /// Building ID (e.g. "Electrolyzer")
+        building: String,
+
+        /// Maximum number of producer steps to follow upstream
+        #[arg(long, default_value = "10")]
+        max_depth: usize,
+    },
+
+    /// Print the neighborhood of a building (producers of its inputs, consumers of its outputs) as DOT
+    BuildingGraph {
+        /// Building ID (e.g. "Electrolyzer")
+        building: String,
+
+        /// Number of building hops to include around it
+        #[arg(long, default_value = "1")]
+        depth: usize,
+
+        #[command(flatten)]
+        style: graph::DotStyle,
+    },
+
+    /// Print the whole building/resource graph as DOT
+    Graph {
+        #[command(flatten)]
+        filter: BuildingFilter,
+
+        #[command(flatten)]
+        style: graph::DotStyle,
+    },
+
+    /// List the buildings that produce a resource
+    Producers {
+        /// Resource ID (e.g. "Oxygen")
+        resource: String,
+
+        /// Sort order
+        #[arg(long, value_enum, default_value = "alphabetical")]
+        order: ProducerOrder,
+    },
+
+    /// List resources with many producers and/or consumers
+    FindHubs {
+        /// Minimum combined producer + consumer count
+        #[arg(long, default_value = "3")]
+        min_degree: usize,
+    },
+
+    /// Change the display name of a building
+    Rename {
+        /// Building ID
+        building: String,
+
+        /// New display name
+        new_name: String,
+    },
+
+    /// Change a building's ID, updating its inputs, outputs, materials, recipes and overrides
+    RenameBuilding {
+        /// Current building ID
+        old_id: String,
+
+        /// New building ID
+        new_id: String,
+    },
+
+    /// Export all building/resource edges as CSV (for NetworkX, Gephi, etc.)
+    ExportEdges {
+        /// Output CSV file
+        output: PathBuf,
+
+        #[command(flatten)]
+        filter: BuildingFilter,
+    },
+
+    /// Export buildings as an editable TOML file
+    ExportToml {
+        /// Output TOML file
+        output: PathBuf,
+
+        #[command(flatten)]
+        filter: BuildingFilter,
+    },
+
+    /// Load buildings from a TOML file written by export-toml
+    LoadToml {
+        /// Input TOML file
+        input: PathBuf,
+    },
+
+    /// Print a TOML skeleton for a new building, to fill in and load with load-toml
+    Template {
+        /// Building ID
+        id: String,
+
+        /// Building category
+        #[arg(long)]
+        category: Option<String>,
+    },
+
+    /// Manage calculator rate overrides
+    Override {
+        #[command(subcommand)]
+        action: OverrideAction,
+    },
+
+    /// Scale building rates by an efficiency factor (e.g. skill bonuses)
+    Efficiency {
+        #[command(subcommand)]
+        action: EfficiencyAction,
+    },
+
+    /// Check key buildings against bundled reference values
+    Sanity,
+
+    /// Report data-quality problems in the database
+    Validate {
+        /// Delete zero-rate and duplicate inputs/outputs and buildings with no I/O
+        #[arg(long)]
+        fix: bool,
+
+        /// Roll the fixes back if they would change more rows than this
+        #[arg(long, default_value_t = 100, requires = "fix")]
+        max_changes: usize,
+    },
+
+    /// Show which resources all buildings together over- or under-supply
+    FlowAnalysis,
+
+    /// Check that one producer building makes a resource at the expected rate (exit code 1 if not)
+    Verify {
+        /// Resource to produce (e.g. "Oxygen")
+        resource: String,
+
+        /// Expected per-building production rate in kg/s
+        expected_rate: f64,
+    },
+
+    /// Show database row counts
+    Stats {
+        /// Also show SQLite storage diagnostics
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Drop the SQL convenience views
+    DropViews,
+
+    /// Drop and recreate the SQL convenience views and any named views
+    RecreateViews,
+
+    /// Create a named SQL view of the buildings matching a filter
+    CreateView {
+        /// View name (letters, digits and underscores)
+        name: String,
+
+        /// Only buildings in this category
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Only buildings from this DLC ("base" for the base game)
+        #[arg(long)]
+        dlc: Option<String>,
+    },
+
+    /// Drop a view created with create-view
+    DropView {
+        /// View name
+        name: String,
+    },
+
+    /// Check GitHub for a newer release (needs the update-check feature)
+    CheckUpdates {
+        /// Give up after this many seconds
+        #[arg(long, default_value = "10")]
+        timeout: u64,
     },
 
     /// Initialize empty database with schema
@@ -68,56 +570,777 @@ enum Commands {
 
     /// Load sample data for testing (without decompiled source)
     LoadSample,
+
+    /// Start an interactive shell with tab completion for building and resource IDs
+    Interactive,
+}
+
+#[derive(Subcommand)]
+enum OverrideAction {
+    /// Override the rate of one building input or output
+    Set {
+        /// Building ID
+        building: String,
+
+        /// Resource ID
+        resource: String,
+
+        /// Replacement rate in kg/s
+        #[arg(value_parser = parse_positive_rate)]
+        rate: f64,
+
+        /// Override an output instead of an input
+        #[arg(long)]
+        output: bool,
+    },
+
+    /// List all overrides
+    List,
+
+    /// Remove all overrides
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum EfficiencyAction {
+    /// Scale every input and output rate of the given buildings
+    Apply {
+        /// `BUILDING=FACTOR` pairs (e.g. Electrolyzer=1.25)
+        #[arg(required = true, value_parser = parse_efficiency_factor)]
+        factors: Vec<(String, f64)>,
+    },
+
+    /// Restore the extracted rates
+    Revert,
+}
+
+/// Parse a `BUILDING=FACTOR` pair for `efficiency apply`
+fn parse_efficiency_factor(s: &str) -> std::result::Result<(String, f64), String> {
+    let (building, factor) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected BUILDING=FACTOR, got '{}'", s))?;
+    let factor = factor
+        .parse()
+        .map_err(|_| format!("invalid factor '{}'", factor))?;
+    Ok((building.to_string(), factor))
+}
+
+/// Parse a rate in kg/s that must be greater than zero
+fn parse_positive_rate(s: &str) -> std::result::Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        Ok(_) => Err(format!("rate must be greater than zero, got '{}'", s)),
+        Err(_) => Err(format!("invalid rate '{}'", s)),
+    }
+}
+
+/// Parse a `RESOURCE=RATE` or `RESOURCE:RATE` pair for `calc-multiple`
+fn parse_target(s: &str) -> std::result::Result<(String, f64), String> {
+    let (resource, rate) = s
+        .split_once(['=', ':'])
+        .ok_or_else(|| format!("expected RESOURCE=RATE, got '{}'", s))?;
+    let rate = rate
+        .parse()
+        .map_err(|_| format!("invalid rate '{}'", rate))?;
+    Ok((resource.to_string(), rate))
+}
+
+/// Sort key for `list-buildings`
+#[derive(Clone, Copy, ValueEnum)]
+enum SortBy {
+    Name,
+    Power,
+    Heat,
+    Category,
+    InputCount,
+    OutputCount,
+    ConstructionTime,
+}
+
+impl SortBy {
+    /// Whether sorting needs columns the summary listing doesn't fetch
+    fn needs_full_building(self) -> bool {
+        matches!(
+            self,
+            SortBy::Heat | SortBy::InputCount | SortBy::OutputCount | SortBy::ConstructionTime
+        )
+    }
+}
+
+/// Sort buildings in memory; the database already returns them by name
+fn sort_buildings(
+    buildings: &mut [Building],
+    sort_by: SortBy,
+    io_counts: &HashMap<String, (usize, usize)>,
+) {
+    match sort_by {
+        SortBy::Name | SortBy::ConstructionTime => {} // Sorted by the query
+        SortBy::Power => buildings.sort_by(|a, b| a.power_watts.total_cmp(&b.power_watts)),
+        SortBy::Heat => buildings.sort_by(|a, b| a.heat_output_dtu.total_cmp(&b.heat_output_dtu)),
+        SortBy::Category => buildings.sort_by(|a, b| a.category.cmp(&b.category)),
+        SortBy::InputCount | SortBy::OutputCount => {
+            let count = |b: &Building| {
+                let (inputs, outputs) = io_counts.get(&b.id).copied().unwrap_or_default();
+                if matches!(sort_by, SortBy::InputCount) {
+                    inputs
+                } else {
+                    outputs
+                }
+            };
+            buildings.sort_by_key(count);
+        }
+    }
+}
+
+/// Resource type filter for `list-resources`
+#[derive(Clone, Copy, ValueEnum)]
+enum ResourceType {
+    Food,
+    Solid,
+    Liquid,
+    Gas,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     let conn = Connection::open(&cli.database)?;
+    db::configure_connection(&conn, !cli.no_wal)?;
     db::init_schema(&conn)?;
+    if let Some(pages) = cli.cache_size {
+        db::set_cache_size(&conn, pages)?;
+    }
 
     match cli.command {
-        Commands::Extract { source_dir, clear } => {
+        Commands::Interactive => interactive::run(&conn, &cli.database),
+        command => run_command(&conn, &cli.database, command),
+    }
+}
+
+/// Run a single subcommand against an open database
+fn run_command(conn: &Connection, database: &Path, command: Commands) -> Result<()> {
+    match command {
+        Commands::Extract {
+            source_dir,
+            clear,
+            profile,
+        } => {
             if clear {
                 println!("Clearing existing data...");
-                db::clear_extracted_data(&conn)?;
+                db::clear_extracted_data(conn)?;
             }
 
-            let stats = extract::extract_to_database(&conn, &source_dir)?;
+            let stats = extract::extract_to_database(conn, &source_dir)?;
             println!("\n{}", stats);
+
+            if profile {
+                println!("\nSlowest files:");
+                for (path, elapsed) in stats.slowest_files(10) {
+                    println!(
+                        "  {:>8.2}ms  {}",
+                        elapsed.as_secs_f64() * 1000.0,
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        Commands::ExtractElements { path } => {
+            let count = extract::extract_elements_to_database(conn, &path)?;
+            println!("\nExtracted {} elements", count);
         }
 
         Commands::Calc {
             resource,
             rate,
             verbose,
+            exclude_dlc,
+            ceil,
+            output_rate_achieved,
+            with_notes,
+            prefer_category,
+            compact,
+            scale,
+            include_extractors,
+            exclude_dupe_operated,
+            strategy,
+            find_loops,
+            credit_byproducts,
+            scenario_file,
+            recipe,
+            temperature,
+            max_power,
+            rate_bound,
+        } => {
+            let options = calculator::CalcOptions {
+                exclude_dlc,
+                round_up: ceil,
+                prefer_category,
+                include_extractors,
+                exclude_dupe_operated,
+                strategy,
+                ..Default::default()
+            };
+            let targets = match (scenario_file, resource) {
+                (Some(path), _) => scenario_file::parse(BufReader::new(File::open(&path)?))?,
+                (None, Some(resource)) => vec![(resource, rate)],
+                (None, None) => unreachable!("clap requires a resource or --scenario-file"),
+            };
+
+            let mut forest = match (&recipe, max_power, targets.as_slice()) {
+                (None, Some(max_watts), [(resource, _)]) => {
+                    let found = calculator::calculate_max_rate_within_power(
+                        conn, resource, max_watts, rate_bound, &options,
+                    );
+                    found.map(|(rate, root)| {
+                        println!(
+                            "Max rate within {:.0}W: {:.3} kg/s {}\n",
+                            max_watts, rate, resource
+                        );
+                        ProductionForest {
+                            roots: vec![root],
+                            targets: vec![(resource.clone(), rate)],
+                        }
+                    })
+                }
+                (Some(recipe), _, [(resource, rate)]) => {
+                    let root = calculator::calculate_production_chain_with_recipe(
+                        conn,
+                        resource,
+                        *rate,
+                        Some(recipe),
+                        &options,
+                    );
+                    root.map(|root| ProductionForest {
+                        roots: vec![root],
+                        targets: targets.clone(),
+                    })
+                }
+                _ => calculator::calculate_multi_target(conn, &targets, &options),
+            }
+            .map_err(|e| anyhow!(explain_chain_error(&e)))?;
+            if let Some(factor) = scale {
+                forest = forest.scale(factor);
+            }
+
+            if verbose {
+                println!("Production chain:\n");
+                if with_notes {
+                    for root in &forest.roots {
+                        let notes = calculator::building_notes(conn, root)?;
+                        println!("{}", calculator::format_production_chain(root, 0, &notes));
+                    }
+                } else {
+                    println!("{}", forest.format_all());
+                }
+            }
+
+            let mut summaries = Vec::new();
+            for (chain, (resource, rate)) in forest.roots.iter().zip(&forest.targets) {
+                let (resource, rate) = (resource.as_str(), *rate);
+                let mut summary = calculator::summarize_chain(chain, resource, rate);
+                if credit_byproducts {
+                    calculator::credit_byproducts(conn, &mut summary, chain)?;
+                }
+                if compact {
+                    println!(
+                        "{} @ {:.3} kg/s: {}",
+                        resource,
+                        rate,
+                        ProductionTree(chain.clone()).format_compact()
+                    );
+                } else {
+                    println!("{}", summary);
+                }
+
+                if verbose {
+                    println!("Result hash: {:016x}", summary.hash());
+                    let counts: Vec<_> = chain
+                        .iter()
+                        .filter(|n| n.is_building())
+                        .map(|n| (n.building_id.clone(), n.count))
+                        .collect();
+                    let seconds = db::total_construction_time_for_chain(conn, &counts)?;
+                    println!("Construction time: {:.0}s (whole buildings)", seconds);
+                }
+
+                if let Some(temp_c) = temperature {
+                    for warning in calculator::state_warnings(conn, chain, temp_c)? {
+                        println!("Warning: {}", warning);
+                    }
+                }
+
+                if find_loops {
+                    let loops = calculator::byproduct_consumers(conn, resource, chain)?;
+                    if !loops.is_empty() {
+                        println!("\nByproduct loop opportunities:");
+                    }
+                    for (producer, byproduct, consumers) in loops {
+                        let names: Vec<_> = consumers.iter().map(|b| b.name.as_str()).collect();
+                        println!(
+                            "  {} -> {}: consumed by {}",
+                            producer,
+                            byproduct,
+                            names.join(", ")
+                        );
+                    }
+                }
+
+                if output_rate_achieved {
+                    println!(
+                        "Requested: {:.3} kg/s {}, Achieved: {:.3} kg/s with integer buildings",
+                        rate,
+                        resource,
+                        calculator::achieved_rate(chain)
+                    );
+                }
+                summaries.push(summary);
+            }
+
+            if forest.roots.len() > 1 {
+                println!("\n=== Combined ({} targets) ===", forest.roots.len());
+                println!("{}", calculator::merge_summaries(&summaries));
+                println!(
+                    "Total: {:.0}W net, {:.0} DTU/s heat",
+                    forest.total_power(),
+                    forest.total_heat()
+                );
+            }
+        }
+
+        Commands::CalcNetwork {
+            dupes,
+            oxygen_per_dupe,
+            water_per_dupe,
+            kcal_per_dupe,
+            food,
+            verbose,
         } => {
-            let chain = calculator::calculate_production_chain(&conn, &resource, rate)?;
+            let rates = calculator::ColonyRates {
+                oxygen_kg_s: oxygen_per_dupe,
+                water_kg_s: water_per_dupe,
+                kcal_per_cycle: kcal_per_dupe,
+                food,
+            };
+            let network = calculator::find_self_sustaining_network(conn, dupes, &rates)?;
 
+            if verbose {
+                for (_, _, chain) in &network.chains {
+                    println!("{}", ProductionTree(chain.clone()));
+                }
+                if let Some(generator) = &network.generator {
+                    println!("{}", ProductionTree(generator.clone()));
+                }
+            }
+            println!("{}", network);
+            println!("{}", network.summary());
+        }
+
+        Commands::CalcMultiple { targets, verbose } => {
+            let forest = calculator::calculate_multi_target(
+                conn,
+                &targets,
+                &calculator::CalcOptions::default(),
+            )
+            .map_err(|e| anyhow!(explain_chain_error(&e)))?;
             if verbose {
                 println!("Production chain:\n");
-                println!("{}", calculator::format_production_chain(&chain, 0));
+                println!("{}", forest.format_all());
+            }
+            println!("{}", forest.summarize());
+            println!(
+                "Total: {:.0}W net, {:.0} DTU/s heat",
+                forest.total_power(),
+                forest.total_heat()
+            );
+        }
+
+        Commands::CalcAll { output, rate } => {
+            let resources = db::list_producible_resources(conn)?;
+            let options = calculator::CalcOptions::default();
+            let mut results = Vec::new();
+            let mut failed = 0;
+            for (i, resource) in resources.iter().enumerate() {
+                eprintln!("[{}/{}] {}", i + 1, resources.len(), resource);
+                // Failures are recorded in the output rather than aborting the run
+                match calculator::calculate_production_chain(conn, resource, rate, &options) {
+                    Ok(chain) => {
+                        let summary = calculator::summarize_chain(&chain, resource, rate);
+                        results.push(serde_json::to_value(summary)?);
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        results.push(serde_json::json!({
+                            "target_resource": resource,
+                            "target_rate": rate,
+                            "error": explain_chain_error(&e),
+                        }));
+                    }
+                }
+            }
+            serde_json::to_writer_pretty(BufWriter::new(File::create(&output)?), &results)?;
+            println!(
+                "Wrote {} results ({} failed) to {}",
+                results.len(),
+                failed,
+                output.display()
+            );
+        }
+
+        Commands::Merge {
+            source_db,
+            conflict,
+            report_file,
+        } => {
+            // Read-only so a mistyped path fails instead of creating an empty database
+            if !source_db.is_file() {
+                return Err(anyhow!(
+                    "source database {} does not exist",
+                    source_db.display()
+                ));
+            }
+            let source = Connection::open_with_flags(&source_db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            // An older source schema is not migrated here; opening it once with --database upgrades it
+            let report = db::merge_database(conn, &source, conflict)
+                .with_context(|| format!("failed to merge from {}", source_db.display()))?;
+            println!(
+                "Merged {} buildings from {}",
+                report.merged_count,
+                source_db.display()
+            );
+            if conflict == MergeConflictMode::Report {
+                serde_json::to_writer_pretty(BufWriter::new(File::create(&report_file)?), &report)?;
+                println!(
+                    "Wrote {} conflicts to {}",
+                    report.conflicts.len(),
+                    report_file.display()
+                );
+            }
+        }
+
+        Commands::Loops { resource, max_len } => {
+            let cycles = db::find_cyclic_chains(conn, &resource, max_len)?;
+            if cycles.is_empty() {
+                println!(
+                    "No production cycles through {} (up to {} buildings)",
+                    resource, max_len
+                );
+                return Ok(());
+            }
+
+            let mut other = 0;
+            for cycle in &cycles {
+                match calculator::analyze_cycle(conn, &resource, cycle)? {
+                    Some(analysis) if analysis.is_beneficial() => println!("{}", analysis),
+                    _ => other += 1,
+                }
+            }
+            println!(
+                "{} cycles found, {} with no net resource or power gain",
+                cycles.len(),
+                other
+            );
+        }
+
+        Commands::WhatIf {
+            building,
+            power_watts,
+            rate,
+        } => {
+            let Some(full) = db::get_full_building(conn, &building)? else {
+                println!("Building '{}' not found", building);
+                return Ok(());
+            };
+            let Some(primary) = full.outputs.first() else {
+                println!("Building '{}' has no outputs", building);
+                return Ok(());
+            };
+
+            // One hypothetical building, pinned as the producer of its primary output
+            let options = calculator::CalcOptions {
+                fixed_producers: HashMap::from([(primary.resource_id.clone(), building.clone())]),
+                hypotheticals: vec![
+                    HypotheticalOverride {
+                        building_id: building.clone(),
+                        field: HypotheticalField::PowerWatts,
+                        value: power_watts,
+                    },
+                    HypotheticalOverride {
+                        building_id: building.clone(),
+                        field: HypotheticalField::OutputRate,
+                        value: rate,
+                    },
+                ],
+                ..Default::default()
+            };
+            let chain =
+                calculator::calculate_production_chain(conn, &primary.resource_id, rate, &options)
+                    .map_err(|e| anyhow!(explain_chain_error(&e)))?;
+
+            println!("*** HYPOTHETICAL - not database values ***");
+            println!(
+                "{}: {:.0} W -> {:.0} W, {} {:.4} kg/s -> {:.4} kg/s\n",
+                full.building.name,
+                full.building.power_watts,
+                power_watts,
+                primary.resource_id,
+                primary.rate_kg_per_s,
+                rate
+            );
+            let tree = ProductionTree(chain);
+            println!("{}", tree);
+            println!(
+                "{}",
+                calculator::summarize_chain(&tree.0, &primary.resource_id, rate)
+            );
+            println!("*** HYPOTHETICAL ***");
+        }
+
+        Commands::RecalcDiff {
+            resource,
+            rate,
+            compare_db,
+        } => {
+            let old_conn = Connection::open(&compare_db)?;
+            db::init_schema(&old_conn)?;
+
+            let options = calculator::CalcOptions::default();
+            let old = calculator::calculate_production_chain(&old_conn, &resource, rate, &options)
+                .map_err(|e| anyhow!("{}: {}", compare_db.display(), explain_chain_error(&e)))?;
+            let new = calculator::calculate_production_chain(conn, &resource, rate, &options)
+                .map_err(|e| anyhow!(explain_chain_error(&e)))?;
+
+            println!(
+                "{} @ {:.3} kg/s: {} -> {}",
+                resource,
+                rate,
+                compare_db.display(),
+                database.display()
+            );
+            print!("{}", ProductionNodeDiff::compute(&old, &new));
+        }
+
+        Commands::Optimize {
+            resource,
+            rate,
+            objective,
+            max_combinations,
+        } => {
+            let (chain, score) = calculator::optimize(
+                conn,
+                &resource,
+                rate,
+                objective,
+                &calculator::CalcOptions::default(),
+                max_combinations,
+            )
+            .map_err(|e| anyhow!(explain_chain_error(&e)))?;
+
+            println!("Best chain by {:?} (score {:.2}):\n", objective, score);
+            println!("{}", ProductionTree(chain.clone()));
+            println!("{}", calculator::summarize_chain(&chain, &resource, rate));
+        }
+
+        Commands::PowerBreakdown { resource, rate } => {
+            let breakdown = db::get_chain_power_breakdown(conn, &resource, rate)?;
+            if breakdown.is_empty() {
+                println!("No building produces {}", resource);
+            } else {
+                for (id, name, power) in &breakdown {
+                    println!("  {:<30} {:<25} {:>10.0}W", name, id, power);
+                }
+                let sql_total: f64 = breakdown.iter().map(|(_, _, power)| power).sum();
+                println!("  {:<56} {:>10.0}W", "Total (SQL)", sql_total);
+
+                let chain = calculator::calculate_production_chain(
+                    conn,
+                    &resource,
+                    rate,
+                    &calculator::CalcOptions::default(),
+                )
+                .map_err(|e| anyhow!(explain_chain_error(&e)))?;
+                let calc_total = calculator::total_power(&chain);
+                println!("  {:<56} {:>10.0}W", "Total (calculator)", calc_total);
+                if (sql_total - calc_total).abs() > 1e-6 * calc_total.abs().max(1.0) {
+                    println!("  Totals differ (rate overrides apply only to the calculator)");
+                }
+            }
+        }
+
+        Commands::CalcFromFile { path } => {
+            let request = calc_file::CalcRequest::load(&path)?;
+            let known = [
+                "exclude_dlc",
+                "ceil",
+                "prefer_category",
+                "compact",
+                "scale",
+                "include_extractors",
+                "exclude_dupe_operated",
+                "strategy",
+                "find_loops",
+                "credit_byproducts",
+                "recipe",
+            ];
+            if let Some(unknown) = request
+                .preferences
+                .keys()
+                .find(|k| !known.contains(&k.as_str()))
+            {
+                return Err(anyhow!(
+                    "unknown preference '{}' (expected one of {})",
+                    unknown,
+                    known.join(", ")
+                ));
             }
+            let scale = match request.preferences.get("scale") {
+                Some(factor) => Some(
+                    factor
+                        .parse()
+                        .map_err(|_| anyhow!("preference scale: invalid factor '{}'", factor))?,
+                ),
+                None => None,
+            };
+            let strategy = match request.preferences.get("strategy") {
+                Some(strategy) => calculator::ProducerStrategy::parse(strategy)
+                    .map_err(|e| anyhow!("preference strategy: {}", e))?,
+                None => calculator::ProducerStrategy::default(),
+            };
+            let calc = Commands::Calc {
+                resource: Some(request.resource.clone()),
+                rate: request.rate,
+                verbose: request.verbose,
+                exclude_dlc: request.flag("exclude_dlc")?,
+                ceil: request.flag("ceil")?,
+                output_rate_achieved: false,
+                with_notes: false,
+                prefer_category: request.preferences.get("prefer_category").cloned(),
+                compact: request.flag("compact")?,
+                scale,
+                include_extractors: request.flag("include_extractors")?,
+                exclude_dupe_operated: request.flag("exclude_dupe_operated")?,
+                strategy,
+                find_loops: request.flag("find_loops")?,
+                credit_byproducts: request.flag("credit_byproducts")?,
+                scenario_file: None,
+                recipe: request.preferences.get("recipe").cloned(),
+                temperature: None,
+                max_power: None,
+                rate_bound: 100.0,
+            };
 
-            let summary = calculator::summarize_chain(&chain, &resource, rate);
-            println!("{}", summary);
+            // Overrides apply to this calculation only: rolled back when `tx` drops
+            let tx = conn.unchecked_transaction()?;
+            db::apply_overrides(&tx, &request.rate_overrides()?)?;
+            run_command(&tx, database, calc)?;
         }
 
-        Commands::ListBuildings => {
-            let buildings = db::list_buildings(&conn)?;
+        Commands::ListBuildings {
+            exclude_dlc,
+            dupe_operated,
+            fully_automatic,
+            full,
+            sort_by,
+            desc,
+        } if !full && !sort_by.needs_full_building() => {
+            let mut buildings = db::list_buildings_summary(conn)?;
+            if exclude_dlc {
+                buildings.retain(|b| b.dlc.is_none());
+            }
+            if dupe_operated || fully_automatic {
+                buildings.retain(|b| b.dupe_operated == dupe_operated);
+            }
+            match sort_by {
+                SortBy::Power => buildings.sort_by(|a, b| a.power_watts.total_cmp(&b.power_watts)),
+                SortBy::Category => buildings.sort_by(|a, b| a.category.cmp(&b.category)),
+                _ => {} // Already sorted by name
+            }
+            if desc {
+                buildings.reverse();
+            }
             if buildings.is_empty() {
                 println!("No buildings in database. Run 'extract' or 'load-sample' first.");
             } else {
-                println!("{:<30} {:>10} {:>10}", "Building", "Power (W)", "Heat (DTU/s)");
-                println!("{}", "-".repeat(52));
+                println!(
+                    "{:<30} {:<25} {:<15} {:>10}",
+                    "Building", "ID", "Category", "Power (W)"
+                );
+                println!("{}", "-".repeat(83));
                 for b in buildings {
-                    println!("{:<30} {:>10.0} {:>10.0}", b.name, b.power_watts, b.heat_output_dtu);
+                    println!(
+                        "{:<30} {:<25} {:<15} {:>10.0}",
+                        b.name,
+                        b.id,
+                        b.category.as_deref().unwrap_or("-"),
+                        b.power_watts
+                    );
                 }
             }
         }
 
-        Commands::ListResources => {
-            let resources = db::list_producible_resources(&conn)?;
+        Commands::ListBuildings {
+            exclude_dlc,
+            dupe_operated,
+            fully_automatic,
+            sort_by,
+            desc,
+            ..
+        } => {
+            let mut buildings = match sort_by {
+                SortBy::ConstructionTime => db::list_buildings_by_construction_time(conn)?,
+                _ => db::list_buildings(conn)?,
+            };
+            if exclude_dlc {
+                buildings.retain(|b| b.dlc.is_none());
+            }
+            if dupe_operated || fully_automatic {
+                buildings.retain(|b| b.dupe_operated == dupe_operated);
+            }
+            let io_counts = db::count_building_io(conn)?;
+            sort_buildings(&mut buildings, sort_by, &io_counts);
+            if desc {
+                buildings.reverse();
+            }
+            if buildings.is_empty() {
+                println!("No buildings in database. Run 'extract' or 'load-sample' first.");
+            } else {
+                println!(
+                    "{:<30} {:>10} {:>12} {:>4} {:>4}",
+                    "Building", "Power (W)", "Heat (DTU/s)", "In", "Out"
+                );
+                println!("{}", "-".repeat(64));
+                for b in buildings {
+                    let (inputs, outputs) = io_counts.get(&b.id).copied().unwrap_or_default();
+                    println!(
+                        "{:<30} {:>10.0} {:>12.0} {:>4} {:>4}",
+                        b.name, b.power_watts, b.heat_output_dtu, inputs, outputs
+                    );
+                }
+            }
+        }
+
+        Commands::ListResources { resource_type } => {
+            let producible = db::list_producible_resources(conn)?;
+            let resources: Vec<String> = match resource_type {
+                None => producible,
+                // Cooked foods come from recipes, not building_outputs
+                Some(ResourceType::Food) => db::list_foods(conn)?
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect(),
+                Some(state) => {
+                    let state = match state {
+                        ResourceType::Solid => "Solid",
+                        ResourceType::Liquid => "Liquid",
+                        _ => "Gas",
+                    };
+                    let matching = db::list_resource_ids_in_state(conn, state)?;
+                    producible
+                        .into_iter()
+                        .filter(|r| matching.contains(r))
+                        .collect()
+                }
+            };
             if resources.is_empty() {
                 println!("No resources in database. Run 'extract' or 'load-sample' first.");
             } else {
@@ -128,50 +1351,614 @@ fn main() -> Result<()> {
             }
         }
 
-        Commands::Building { id } => {
-            let buildings = db::list_buildings(&conn)?;
-            if let Some(b) = buildings.iter().find(|b| b.id == id) {
-                println!("Building: {}", b.name);
-                println!("  ID: {}", b.id);
-                println!("  Power: {}W", b.power_watts);
-                println!("  Heat: {} DTU/s", b.heat_output_dtu);
+        Commands::CalcHeatOnly {
+            heat_load,
+            ambient_temp,
+        } => {
+            let solution = calculator::find_cooling_solution(conn, heat_load, ambient_temp)?;
+            if solution.is_empty() {
+                println!(
+                    "No cooling building in the database works at {}°C",
+                    ambient_temp
+                );
+                return Ok(());
+            }
+            println!("Cooling {:.0} DTU/s at {}°C:", heat_load, ambient_temp);
+            for (building, count) in &solution {
+                println!(
+                    "  {:.0}x {} ({:.0} DTU/s removed, {:.0}W)",
+                    count,
+                    building.name,
+                    -count * building.heat_output_dtu,
+                    count * building.power_watts
+                );
+            }
+            let cooling: f64 = solution
+                .iter()
+                .map(|(b, count)| -count * b.heat_output_dtu)
+                .sum();
+            let consumed = solution
+                .iter()
+                .filter(|(b, _)| b.power_watts < 0.0)
+                .fold(0.0, |total, (b, count)| total - count * b.power_watts);
+            println!(
+                "Total: {:.0} DTU/s removed, {:.0}W consumed",
+                cooling, consumed
+            );
+        }
+
+        Commands::ListCoolers => {
+            let coolers = db::get_buildings_with_heat_cooling(conn)?;
+            if coolers.is_empty() {
+                println!("No cooling buildings in database. Run 'extract' first.");
+            } else {
+                println!(
+                    "{:<30} {:<25} {:>14} {:>10}",
+                    "Building", "ID", "Heat (DTU/s)", "kDTU/s"
+                );
+                println!("{}", "-".repeat(82));
+                for b in coolers {
+                    println!(
+                        "{:<30} {:<25} {:>14.0} {:>10.3}",
+                        b.name,
+                        b.id,
+                        b.heat_output_dtu,
+                        b.heat_output_dtu / 1000.0
+                    );
+                }
+            }
+        }
 
-                let inputs = db::get_building_inputs(&conn, &id)?;
-                if !inputs.is_empty() {
-                    println!("  Inputs:");
-                    for i in inputs {
-                        println!("    {} @ {} kg/s", i.resource_id, i.rate_kg_per_s);
+        Commands::ListFoods => {
+            let foods = db::list_foods(conn)?;
+            if foods.is_empty() {
+                println!("No foods in database. Run 'extract' first.");
+            } else {
+                println!("{:<30} {:>12}", "Food", "kcal/unit");
+                println!("{}", "-".repeat(43));
+                for (food, calories) in foods {
+                    match calories {
+                        Some(kcal) => println!("{:<30} {:>12.0}", food, kcal),
+                        None => println!("{:<30} {:>12}", food, "?"),
                     }
                 }
+            }
+        }
+
+        Commands::Resource { id: Some(id) } => match db::get_resource(conn, &id)? {
+            Some(resource) => print!("{}", resource),
+            None => println!("Resource '{}' not found. Run 'extract-elements' first.", id),
+        },
+
+        Commands::Resource { id: None } => {
+            let resources = db::list_resources(conn)?;
+            if resources.is_empty() {
+                println!("No resources in database. Run 'extract-elements' first.");
+            } else {
+                let value =
+                    |v: Option<f64>| v.map(|v| format!("{:.3}", v)).unwrap_or("?".to_string());
+                println!(
+                    "{:<20} {:<7} {:>10} {:>10} {:>10} {:>10}",
+                    "Resource", "State", "SHC", "TC", "Melt °C", "Boil °C"
+                );
+                println!("{}", "-".repeat(72));
+                for r in resources {
+                    println!(
+                        "{:<20} {:<7} {:>10} {:>10} {:>10} {:>10}",
+                        r.id,
+                        r.state.as_deref().unwrap_or("?"),
+                        value(r.specific_heat_capacity),
+                        value(r.thermal_conductivity),
+                        value(r.melt_point_c),
+                        value(r.boil_point_c)
+                    );
+                }
+            }
+        }
+
+        Commands::ListFoodRecipes => {
+            let recipes = db::list_food_recipes(conn)?;
+            if recipes.is_empty() {
+                println!("No food recipes in database. Run 'extract' first.");
+            } else {
+                let mut ranked: Vec<_> = recipes
+                    .iter()
+                    .map(|(recipe, inputs, outputs)| {
+                        (recipe, recipe.calories_per_kg_input(inputs, outputs))
+                    })
+                    .collect();
+                // Recipes without known calories sort last
+                ranked.sort_by(|a, b| {
+                    b.1.unwrap_or(f64::NEG_INFINITY)
+                        .total_cmp(&a.1.unwrap_or(f64::NEG_INFINITY))
+                });
+
+                println!(
+                    "{:<25} {:<25} {:>14}",
+                    "Building", "Recipe", "kcal/kg input"
+                );
+                println!("{}", "-".repeat(66));
+                for (recipe, calories) in ranked {
+                    match calories {
+                        Some(kcal) => println!(
+                            "{:<25} {:<25} {:>14.0}",
+                            recipe.building_id, recipe.name, kcal
+                        ),
+                        None => {
+                            println!("{:<25} {:<25} {:>14}", recipe.building_id, recipe.name, "?")
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Building { id, borders } => match db::get_full_building(conn, &id)? {
+            Some(full) if borders => print!("{:#}", full),
+            Some(full) => print!("{}", full),
+            None => println!("Building '{}' not found", id),
+        },
+
+        Commands::BuildingDiff { before, after } => {
+            match db::compare_buildings(conn, &before, &after)? {
+                Some(comparison) => print!("{}", comparison),
+                None => {
+                    let missing = if db::get_building(conn, &before)?.is_none() {
+                        before
+                    } else {
+                        after
+                    };
+                    println!("Building '{}' not found", missing);
+                }
+            }
+        }
 
-                let producers = db::get_producers(&conn, &id)?;
-                // This is backwards - let's get outputs instead
-                // For now, just query directly
-                let mut stmt = conn.prepare(
-                    "SELECT resource_id, rate_kg_per_s FROM building_outputs WHERE building_id = ?",
-                )?;
-                let outputs: Vec<(String, f64)> = stmt
-                    .query_map([&id], |row| Ok((row.get(0)?, row.get(1)?)))?
-                    .filter_map(|r| r.ok())
+        Commands::RecipeEfficiency { building } => match db::get_full_building(conn, &building)? {
+            Some(full) if full.recipes.is_empty() => println!("{} has no recipes", building),
+            Some(full) => {
+                let mut ranked: Vec<_> = full
+                    .recipes
+                    .iter()
+                    .map(|(recipe, inputs, outputs)| {
+                        (recipe, recipe.mass_efficiency(inputs, outputs))
+                    })
                     .collect();
+                // Most efficient first; recipes without inputs sort last
+                ranked.sort_by(|a, b| {
+                    b.1.unwrap_or(f64::NEG_INFINITY)
+                        .total_cmp(&a.1.unwrap_or(f64::NEG_INFINITY))
+                });
+
+                println!("Recipes of {} by mass efficiency:", full.building.name);
+                for (recipe, efficiency) in ranked {
+                    match efficiency {
+                        Some(e) => println!("  {:>7.1}%  {}", e * 100.0, recipe.name),
+                        None => println!("  {:>8}  {}", "-", recipe.name),
+                    }
+                }
+            }
+            None => println!("Building '{}' not found", building),
+        },
+
+        Commands::FindAlternatives { building } => {
+            if db::get_building(conn, &building)?.is_none() {
+                println!("Building '{}' not found", building);
+            } else {
+                let alternatives = db::find_substitute_buildings(conn, &building)?;
+                if alternatives.is_empty() {
+                    println!("No alternatives to {} found", building);
+                } else {
+                    println!("Alternatives to {}:", building);
+                    for b in alternatives {
+                        println!(
+                            "  {:<30} {:>10.0}W  {}",
+                            b.name,
+                            b.power_watts,
+                            b.dlc.as_deref().unwrap_or("")
+                        );
+                    }
+                }
+            }
+        }
+
+        Commands::Reachable {
+            resource,
+            max_steps,
+        } => {
+            let reachable = db::get_buildings_reachable_from(conn, &resource, max_steps)?;
+            if reachable.is_empty() {
+                println!("No buildings consume {}", resource);
+            } else {
+                println!(
+                    "Buildings reachable from {} (within {} steps):",
+                    resource, max_steps
+                );
+                for (b, distance) in reachable {
+                    println!("  {:>2}  {:<30} {}", distance, b.name, b.id);
+                }
+            }
+        }
+
+        Commands::TransitiveInputs {
+            building,
+            max_depth,
+        } => {
+            if db::get_building(conn, &building)?.is_none() {
+                println!("Building '{}' not found", building);
+                return Ok(());
+            }
+            let resources = db::get_transitive_inputs(conn, &building, max_depth)?;
+            if resources.is_empty() {
+                println!("{} has no inputs", building);
+            } else {
+                println!("Resources needed to run {}:", building);
+                for resource in resources {
+                    println!("  {}", resource);
+                }
+            }
+        }
+
+        Commands::BuildingGraph {
+            building,
+            depth,
+            style,
+        } => {
+            if db::get_building(conn, &building)?.is_none() {
+                println!("Building '{}' not found", building);
+                return Ok(());
+            }
+            let edges = db::list_all_edges(conn, &BuildingFilter::default())?;
+            let local = graph::neighborhood(&edges, &building, depth);
+            print!("{}", graph::to_dot(&local, Some(&building), &style));
+        }
+
+        Commands::Graph { filter, style } => {
+            let edges = db::list_all_edges(conn, &filter)?;
+            let all: Vec<_> = edges.iter().collect();
+            print!("{}", graph::to_dot(&all, None, &style));
+        }
+
+        Commands::Producers { resource, order } => {
+            let producers = db::get_producers_ordered_by(conn, &resource, order)?;
+            if producers.is_empty() {
+                println!("No building produces '{}'", resource);
+            } else {
+                println!(
+                    "{:<30} {:>10} {:>10} {:>12}",
+                    "Building", "kg/s", "Power (W)", "Heat (DTU/s)"
+                );
+                println!("{}", "-".repeat(65));
+                for (building, rate) in producers {
+                    println!(
+                        "{:<30} {:>10.4} {:>10.0} {:>12.0}",
+                        building.name, rate, building.power_watts, building.heat_output_dtu
+                    );
+                }
+            }
+        }
+
+        Commands::FindHubs { min_degree } => {
+            let mut hubs = db::find_hub_resources(conn)?;
+            hubs.retain(|(_, producers, consumers)| producers + consumers >= min_degree);
+            if hubs.is_empty() {
+                println!("No resources with degree >= {}", min_degree);
+            } else {
+                println!(
+                    "{:<25} {:>10} {:>10} {:>8}",
+                    "Resource", "Producers", "Consumers", "Degree"
+                );
+                println!("{}", "-".repeat(56));
+                for (resource, producers, consumers) in hubs {
+                    println!(
+                        "{:<25} {:>10} {:>10} {:>8}",
+                        resource,
+                        producers,
+                        consumers,
+                        producers + consumers
+                    );
+                }
+            }
+        }
+
+        Commands::Rename { building, new_name } => match db::get_building(conn, &building)? {
+            Some(b) => {
+                db::update_building_name(conn, &building, &new_name)?;
+                println!("Renamed {}: '{}' -> '{}'", b.id, b.name, new_name);
+            }
+            None => println!("Building '{}' not found", building),
+        },
+
+        Commands::RenameBuilding { old_id, new_id } => {
+            db::rename_building(conn, &old_id, &new_id)?;
+            println!("Renamed building ID {} -> {}", old_id, new_id);
+        }
+
+        Commands::ExportEdges { output, filter } => {
+            let edges = db::list_all_edges(conn, &filter)?;
+            let mut file = BufWriter::new(File::create(&output)?);
+            writeln!(file, "building_id,resource_id,direction,rate_kg_per_s")?;
+            for (building, resource, direction, rate) in &edges {
+                writeln!(file, "{},{},{},{}", building, resource, direction, rate)?;
+            }
+            file.flush()?;
+            println!("Wrote {} edges to {}", edges.len(), output.display());
+        }
+
+        Commands::ExportToml { output, filter } => {
+            db::export_toml(conn, &filter, BufWriter::new(File::create(&output)?))?;
+            println!("Exported buildings to {}", output.display());
+        }
+
+        Commands::LoadToml { input } => {
+            let count = db::load_from_toml(conn, File::open(&input)?)?;
+            println!("Loaded {} buildings from {}", count, input.display());
+        }
+
+        Commands::Template { id, category } => {
+            print!("{}", db::building_template_toml(&id, category.as_deref())?);
+        }
+
+        Commands::Override { action } => match action {
+            OverrideAction::Set {
+                building,
+                resource,
+                rate,
+                output,
+            } => {
+                if db::get_building(conn, &building)?.is_none() {
+                    println!("Building '{}' not found", building);
+                } else {
+                    let direction = if output { "output" } else { "input" };
+                    db::apply_overrides(
+                        conn,
+                        &[RateOverride {
+                            building_id: building.clone(),
+                            resource_id: resource.clone(),
+                            direction: direction.to_string(),
+                            rate_kg_per_s: rate,
+                        }],
+                    )?;
+                    println!(
+                        "Override set: {} {} {} @ {} kg/s",
+                        building, direction, resource, rate
+                    );
+                }
+            }
+            OverrideAction::List => {
+                let overrides = db::list_overrides(conn)?;
+                if overrides.is_empty() {
+                    println!("No overrides set");
+                } else {
+                    println!(
+                        "{:<30} {:<8} {:<20} {:>10}",
+                        "Building", "Dir", "Resource", "kg/s"
+                    );
+                    println!("{}", "-".repeat(71));
+                    for o in overrides {
+                        println!(
+                            "{:<30} {:<8} {:<20} {:>10.4}",
+                            o.building_id, o.direction, o.resource_id, o.rate_kg_per_s
+                        );
+                    }
+                }
+            }
+            OverrideAction::Clear => {
+                let count = db::clear_overrides(conn)?;
+                println!("Removed {} overrides", count);
+            }
+        },
+
+        Commands::Efficiency { action } => match action {
+            EfficiencyAction::Apply { factors } => {
+                for (building, _) in &factors {
+                    if db::get_building(conn, building)?.is_none() {
+                        println!("Building '{}' not found", building);
+                        return Ok(());
+                    }
+                }
+                db::apply_efficiency_table(conn, &factors)?;
+                for (building, factor) in &factors {
+                    println!("Scaled {} rates by {}", building, factor);
+                }
+            }
+            EfficiencyAction::Revert => {
+                let count = db::revert_efficiency_table(conn)?;
+                println!("Restored {} rates", count);
+            }
+        },
+
+        Commands::Sanity => {
+            let results = sanity::check_reference(conn)?;
+            let failed = results.iter().filter(|r| !r.issues.is_empty()).count();
 
-                if !outputs.is_empty() {
-                    println!("  Outputs:");
-                    for (res, rate) in outputs {
-                        println!("    {} @ {} kg/s", res, rate);
+            println!(
+                "Checked {} reference buildings (tolerance {:.0}%):",
+                results.len(),
+                sanity::TOLERANCE * 100.0
+            );
+            for result in &results {
+                if result.issues.is_empty() {
+                    println!("  OK    {}", result.building_id);
+                } else {
+                    println!("  FAIL  {}", result.building_id);
+                    for issue in &result.issues {
+                        println!("          {}", issue);
                     }
                 }
+            }
+            println!(
+                "\n{} of {} buildings deviate from reference values",
+                failed,
+                results.len()
+            );
+        }
+
+        Commands::Validate { fix, max_changes } => {
+            let missing_io = db::list_buildings_missing_io(conn)?;
+            println!(
+                "Likely extraction errors (no inputs, outputs or recipes): {}",
+                missing_io.len()
+            );
+            for b in &missing_io {
+                println!("  {:<30} {}", b.name, b.id);
+            }
+            for (issue, count) in db::count_io_issues(conn)? {
+                println!("Rows with {}: {}", issue, count);
+            }
+
+            if fix {
+                let fixes = db::fix_data_quality(conn, max_changes)?;
+                if fixes.is_empty() {
+                    println!("Nothing to fix");
+                }
+                for description in fixes {
+                    println!("Fixed: {}", description);
+                }
+            }
+        }
+
+        Commands::FlowAnalysis => {
+            let flow = db::analyze_resource_flow(conn)?;
+            println!("Over-supplied ({}):", flow.over_supplied.len());
+            for (resource, net) in &flow.over_supplied {
+                println!("  {:<25} {:>+10.4} kg/s", resource, net);
+            }
+            println!("Under-supplied ({}):", flow.under_supplied.len());
+            for (resource, net) in &flow.under_supplied {
+                println!("  {:<25} {:>+10.4} kg/s", resource, net);
+            }
+            println!(
+                "Balanced ({}): {}",
+                flow.balanced.len(),
+                flow.balanced.join(", ")
+            );
+        }
+
+        Commands::Verify {
+            resource,
+            expected_rate,
+        } => {
+            let chain = calculator::calculate_production_chain(
+                conn,
+                &resource,
+                expected_rate,
+                &calculator::CalcOptions::default(),
+            )
+            .map_err(|e| anyhow!(explain_chain_error(&e)))?;
+
+            if sanity::deviates(chain.output_rate, expected_rate) {
+                return Err(anyhow!(
+                    "{} produces {:.4} kg/s {}, expected {:.4} kg/s (tolerance {:.0}%)",
+                    chain.building_name,
+                    chain.output_rate,
+                    resource,
+                    expected_rate,
+                    sanity::TOLERANCE * 100.0
+                ));
+            }
+            println!(
+                "OK: {} produces {:.4} kg/s {} (expected {:.4} kg/s)",
+                chain.building_name, chain.output_rate, resource, expected_rate
+            );
+        }
+
+        Commands::Stats { verbose } => {
+            println!("Database: {}", database.display());
+            if let Some(version) = db::get_metadata(conn, db::METADATA_GAME_VERSION)? {
+                println!("Game version: {}", version);
+            }
+            for table in [
+                "buildings",
+                "building_inputs",
+                "building_outputs",
+                "building_materials",
+                "resources",
+                "recipes",
+            ] {
+                println!("  {:<20} {:>8}", table, db::count_rows(conn, table)?);
+            }
+
+            let power = db::get_building_efficiency_stats(conn)?;
+            if power.total_building_count > 0 {
+                println!();
+                println!("Power ({} buildings):", power.total_building_count);
+                println!("  Min:  {:>10.0} W", power.min_power_w);
+                println!("  Max:  {:>10.0} W", power.max_power_w);
+                println!("  Mean: {:>10.1} W", power.mean_power_w);
+            }
+
+            if verbose {
+                let page_size = db::pragma_page_size(conn)?;
+                let page_count = db::pragma_page_count(conn)?;
+                println!();
+                println!("SQLite:");
+                println!("  Page size:    {} bytes", page_size);
+                println!("  Page count:   {}", page_count);
+                println!("  File size:    {} KiB", page_size * page_count / 1024);
+                println!("  Cache size:   {}", db::pragma_cache_size(conn)?);
+                println!("  Journal mode: {}", db::pragma_journal_mode(conn)?);
+            }
+        }
+
+        Commands::DropViews => {
+            db::drop_views(conn)?;
+            println!("Dropped views (they are recreated the next time the database is opened)");
+        }
+
+        Commands::RecreateViews => {
+            db::drop_views(conn)?;
+            db::create_view_production_summary(conn)?;
+            let user_views = db::recreate_user_views(conn)?;
+            println!("Recreated views ({} named)", user_views);
+        }
+
+        Commands::CreateView {
+            name,
+            category,
+            dlc,
+        } => {
+            let filter = BuildingFilter {
+                category,
+                dlc,
+                name_contains: None,
+                since: None,
+            };
+            db::create_building_view(conn, &name, &filter)?;
+            println!("Created view {}", name);
+        }
+
+        Commands::DropView { name } => {
+            if db::drop_building_view(conn, &name)? {
+                println!("Dropped view {}", name);
             } else {
-                println!("Building '{}' not found", id);
+                println!("View '{}' not found", name);
             }
         }
 
         Commands::Init => {
-            println!("Database initialized at: {}", cli.database.display());
+            println!("Database initialized at: {}", database.display());
+        }
+
+        Commands::CheckUpdates { timeout } => {
+            let current = env!("CARGO_PKG_VERSION");
+            match update_check::latest_release_tag(Duration::from_secs(timeout)) {
+                Ok(tag) if update_check::is_newer(&tag, current) => {
+                    println!(
+                        "Update available: v{} (running v{})",
+                        tag.trim_start_matches('v'),
+                        current
+                    )
+                }
+                Ok(_) => println!("Up to date (v{})", current),
+                Err(e) => println!("Could not check for updates: {}", e),
+            }
+        }
+
+        Commands::Interactive => {
+            println!("Already in interactive mode");
         }
 
         Commands::LoadSample => {
-            load_sample_data(&conn)?;
+            load_sample_data(conn)?;
             println!("Sample data loaded successfully!");
         }
     }
@@ -179,9 +1966,33 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Turn a production chain error into a message with a hint on what to do next
+fn explain_chain_error(err: &ProductionChainError) -> String {
+    match err {
+        ProductionChainError::NoProducers { resource } => format!(
+            "No building in the database produces '{}'. Run 'list-resources' to see what can be made.",
+            resource
+        ),
+        ProductionChainError::InvalidOutputRate {
+            building,
+            resource,
+            rate,
+        } => format!(
+            "{} makes {} at {} kg/s, so no number of them can meet the target. \
+             Check 'override list' or re-run 'extract'.",
+            building, resource, rate
+        ),
+        ProductionChainError::Other(e) => format!("{:#}", e),
+        ProductionChainError::DatabaseError(e) => format!(
+            "Database error: {}. Try re-running 'extract' or 'load-sample'.",
+            e
+        ),
+    }
+}
+
 /// Load sample ONI building data for testing without decompiled source
 fn load_sample_data(conn: &Connection) -> Result<()> {
-    use crate::models::{Building, BuildingInput, BuildingOutput};
+    use crate::models::{BuildingInput, BuildingOutput, BuildingType};
 
     db::clear_extracted_data(conn)?;
 
@@ -193,6 +2004,14 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         power_watts: -120.0,
         heat_output_dtu: 1000.0,
         construction_time_s: Some(30.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &electrolyzer)?;
     db::insert_building_input(
@@ -209,6 +2028,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "Electrolyzer".to_string(),
             resource_id: "Oxygen".to_string(),
             rate_kg_per_s: 0.888,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
     db::insert_building_output(
@@ -217,6 +2037,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "Electrolyzer".to_string(),
             resource_id: "Hydrogen".to_string(),
             rate_kg_per_s: 0.112,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
 
@@ -228,6 +2049,14 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         power_watts: 800.0, // Generates power
         heat_output_dtu: 2000.0,
         construction_time_s: Some(120.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &h2_gen)?;
     db::insert_building_input(
@@ -247,6 +2076,14 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         power_watts: 600.0,
         heat_output_dtu: 9000.0,
         construction_time_s: Some(120.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &coal_gen)?;
     db::insert_building_input(
@@ -263,6 +2100,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "Generator".to_string(),
             resource_id: "CarbonDioxide".to_string(),
             rate_kg_per_s: 0.02,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
 
@@ -274,6 +2112,14 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         power_watts: -120.0,
         heat_output_dtu: 500.0,
         construction_time_s: Some(30.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &sieve)?;
     db::insert_building_input(
@@ -298,6 +2144,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "WaterPurifier".to_string(),
             resource_id: "Water".to_string(),
             rate_kg_per_s: 5.0,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
     db::insert_building_output(
@@ -306,6 +2153,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "WaterPurifier".to_string(),
             resource_id: "ToxicSand".to_string(),
             rate_kg_per_s: 0.2,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
 
@@ -317,6 +2165,14 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         power_watts: -1200.0,
         heat_output_dtu: 16000.0,
         construction_time_s: Some(120.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &refinery)?;
     db::insert_building_input(
@@ -333,6 +2189,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "MetalRefinery".to_string(),
             resource_id: "Iron".to_string(),
             rate_kg_per_s: 0.5,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
 
@@ -341,9 +2198,17 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         id: "AlgaeHabitat".to_string(),
         name: "Algae Terrarium".to_string(),
         category: Some("Oxygen".to_string()),
-        power_watts: 0.0, // No power required
+        power_watts: 0.0,        // No power required
         heat_output_dtu: -667.0, // Cools!
         construction_time_s: Some(30.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &terrarium)?;
     db::insert_building_input(
@@ -368,6 +2233,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "AlgaeHabitat".to_string(),
             resource_id: "Oxygen".to_string(),
             rate_kg_per_s: 0.040,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
     db::insert_building_output(
@@ -376,6 +2242,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "AlgaeHabitat".to_string(),
             resource_id: "DirtyWater".to_string(),
             rate_kg_per_s: 0.290,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
 
@@ -387,6 +2254,14 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
         power_watts: 800.0,
         heat_output_dtu: 10000.0,
         construction_time_s: Some(120.0),
+        dlc: None,
+        building_type: BuildingType::Production,
+        min_critters: None,
+        extractor: false,
+        dupe_operated: false,
+        passive_thermal: false,
+        thermal_mass: None,
+        heat_conductivity: None,
     };
     db::upsert_building(conn, &natgas_gen)?;
     db::insert_building_input(
@@ -403,6 +2278,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "MethaneGenerator".to_string(),
             resource_id: "CarbonDioxide".to_string(),
             rate_kg_per_s: 0.0225,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
     db::insert_building_output(
@@ -411,6 +2287,7 @@ fn load_sample_data(conn: &Connection) -> Result<()> {
             building_id: "MethaneGenerator".to_string(),
             resource_id: "DirtyWater".to_string(),
             rate_kg_per_s: 0.0675,
+            output_type: BuildingOutputType::Mass,
         },
     )?;
 
@@ -1,5 +1,11 @@
 //! Data models for ONI buildings and resources
 
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use thiserror::Error;
+
 #[derive(Debug, Clone)]
 pub struct Resource {
     pub id: String,
@@ -11,64 +17,1171 @@ pub struct Resource {
     pub boil_point_c: Option<f64>,
 }
 
-#[derive(Debug, Clone)]
+impl Resource {
+    /// State of this resource at `temp_c`, or `None` without any melt/boil point
+    pub fn state_at_temperature(&self, temp_c: f64) -> Option<ResourceState> {
+        if self.melt_point_c.is_none() && self.boil_point_c.is_none() {
+            return None;
+        }
+        if self.melt_point_c.is_some_and(|melt| temp_c < melt) {
+            Some(ResourceState::Solid)
+        } else if self.boil_point_c.is_some_and(|boil| temp_c >= boil) {
+            Some(ResourceState::Gas)
+        } else {
+            Some(ResourceState::Liquid)
+        }
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |v: Option<f64>, unit: &str| {
+            v.map(|v| format!("{} {}", v, unit))
+                .unwrap_or("?".to_string())
+        };
+
+        writeln!(f, "Resource: {}", self.name)?;
+        writeln!(f, "  ID: {}", self.id)?;
+        writeln!(f, "  State: {}", self.state.as_deref().unwrap_or("?"))?;
+        writeln!(
+            f,
+            "  Specific heat: {}",
+            value(self.specific_heat_capacity, "DTU/g/°C")
+        )?;
+        writeln!(
+            f,
+            "  Thermal conductivity: {}",
+            value(self.thermal_conductivity, "DTU/(m·s·°C)")
+        )?;
+        writeln!(f, "  Melting point: {}", value(self.melt_point_c, "°C"))?;
+        writeln!(f, "  Boiling point: {}", value(self.boil_point_c, "°C"))
+    }
+}
+
+/// Physical state of a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceState {
+    Solid,
+    Liquid,
+    Gas,
+}
+
+impl ResourceState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResourceState::Solid => "Solid",
+            ResourceState::Liquid => "Liquid",
+            ResourceState::Gas => "Gas",
+        }
+    }
+
+    /// Parse a stored `resources.state`; unknown values give `None`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Solid" => Some(ResourceState::Solid),
+            "Liquid" => Some(ResourceState::Liquid),
+            "Gas" => Some(ResourceState::Gas),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Building {
     pub id: String,
     pub name: String,
     pub category: Option<String>,
-    pub power_watts: f64,       // Negative = consumes, Positive = generates
+    pub power_watts: f64, // Negative = consumes, Positive = generates
     pub heat_output_dtu: f64,
     pub construction_time_s: Option<f64>,
+    pub dlc: Option<String>, // None = base game
+    pub building_type: BuildingType,
+    pub min_critters: Option<u32>, // Ranch/shearing stations need critters to operate
+    pub extractor: bool, // Produces from the map (e.g. Oil Well) without consuming anything
+    pub dupe_operated: bool, // Needs a duplicant working it; rates are the unskilled base values
+    pub passive_thermal: bool, // Only changes how heat moves (Insulated Tile), so has no inputs or outputs
+    pub thermal_mass: Option<f64>, // Overrides the default thermal mass when the config sets one
+    pub heat_conductivity: Option<f64>, // Multiplier on the material's conductivity (Insulated Tile = 0.01)
+}
+
+/// `building_id` of production chain nodes that stand for a raw input rather than a building
+pub const RAW_RESOURCE_ID: &str = "RAW_RESOURCE";
+
+/// `building_id` of production chain nodes where an input leads back to a resource already being resolved
+pub const CYCLE_BREAK_ID: &str = "CYCLE_BREAK";
+
+impl Building {
+    /// Placeholder "building" supplying `resource_id` as a raw input
+    pub fn new_raw_resource(resource_id: &str) -> Building {
+        Building {
+            id: RAW_RESOURCE_ID.to_string(),
+            name: format!("{} (raw input)", resource_id),
+            category: None,
+            power_watts: 0.0,
+            heat_output_dtu: 0.0,
+            construction_time_s: None,
+            dlc: None,
+            building_type: BuildingType::default(),
+            min_critters: None,
+            extractor: false,
+            dupe_operated: false,
+            passive_thermal: false,
+            thermal_mass: None,
+            heat_conductivity: None,
+        }
+    }
+
+    /// Whether this is a placeholder made by `new_raw_resource`
+    pub fn is_raw_resource_sentinel(&self) -> bool {
+        self.id == RAW_RESOURCE_ID
+    }
 }
 
+/// The few building columns needed for listings
 #[derive(Debug, Clone)]
+pub struct BuildingSummary {
+    pub id: String,
+    pub name: String,
+    pub category: Option<String>,
+    pub power_watts: f64,
+    pub dlc: Option<String>, // Needed for --exclude-dlc filtering
+    pub dupe_operated: bool, // Needed for --dupe-operated/--fully-automatic filtering
+}
+
+/// What a building's inputs and outputs represent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum BuildingType {
+    /// Converts resources into other resources (the normal case)
+    #[default]
+    Production,
+    /// Affects duplicants (skills, morale) rather than producing resources
+    Services,
+}
+
+impl BuildingType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildingType::Production => "Production",
+            BuildingType::Services => "Services",
+        }
+    }
+
+    /// Parse a stored type name, defaulting to `Production` for unknown values
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Services" => BuildingType::Services,
+            _ => BuildingType::Production,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct BuildingInput {
     pub building_id: String,
     pub resource_id: String,
     pub rate_kg_per_s: f64,
 }
 
-#[derive(Debug, Clone)]
+/// What a building output's rate measures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum BuildingOutputType {
+    /// A resource in kg/s (the normal case)
+    #[default]
+    Mass,
+    /// Rocket engine thrust in Newtons
+    Thrust,
+    /// Electrical power in watts
+    Power,
+    /// Research (discovery) points per cycle
+    Research,
+}
+
+impl BuildingOutputType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildingOutputType::Mass => "mass",
+            BuildingOutputType::Thrust => "thrust",
+            BuildingOutputType::Power => "power",
+            BuildingOutputType::Research => "research",
+        }
+    }
+
+    /// Unit of the rate stored for this output type
+    pub fn unit(&self) -> &'static str {
+        match self {
+            BuildingOutputType::Mass => "kg/s",
+            BuildingOutputType::Thrust => "N",
+            BuildingOutputType::Power => "W",
+            BuildingOutputType::Research => "points/cycle",
+        }
+    }
+
+    /// Parse a stored type name, defaulting to `Mass` for unknown values
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "thrust" => BuildingOutputType::Thrust,
+            "power" => BuildingOutputType::Power,
+            "research" => BuildingOutputType::Research,
+            _ => BuildingOutputType::Mass,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct BuildingOutput {
     pub building_id: String,
     pub resource_id: String,
-    pub rate_kg_per_s: f64,
+    pub rate_kg_per_s: f64, // In `output_type.unit()` for non-mass outputs
+    pub output_type: BuildingOutputType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Recipe {
     pub id: i64,
     pub building_id: String,
     pub name: String,
+    pub cycle_time_s: Option<f64>, // Seconds per fabrication, if known
+    pub recipe_type: Option<String>, // "Food" for cooking recipes
 }
 
-#[derive(Debug, Clone)]
+/// A recipe together with its inputs and outputs
+pub type RecipeWithIo = (Recipe, Vec<RecipeInput>, Vec<RecipeOutput>);
+
+impl Recipe {
+    /// Total kg/s consumed by this recipe (entries of other recipes are ignored)
+    pub fn inputs_sum_rate(&self, inputs: &[RecipeInput]) -> f64 {
+        inputs
+            .iter()
+            .filter(|i| i.recipe_id == self.id)
+            .map(|i| i.rate_kg_per_s)
+            .sum()
+    }
+
+    /// Total kg/s produced by this recipe (entries of other recipes are ignored)
+    pub fn outputs_sum_rate(&self, outputs: &[RecipeOutput]) -> f64 {
+        outputs
+            .iter()
+            .filter(|o| o.recipe_id == self.id)
+            .map(|o| o.rate_kg_per_s)
+            .sum()
+    }
+
+    /// Output mass per unit of input mass; `None` if the recipe has no inputs
+    pub fn mass_efficiency(&self, inputs: &[RecipeInput], outputs: &[RecipeOutput]) -> Option<f64> {
+        let consumed = self.inputs_sum_rate(inputs);
+        (consumed > 0.0).then(|| self.outputs_sum_rate(outputs) / consumed)
+    }
+
+    /// Calories produced per kg of input mass; `None` if the recipe has no inputs
+    /// or none of its outputs have known calories
+    pub fn calories_per_kg_input(
+        &self,
+        inputs: &[RecipeInput],
+        outputs: &[RecipeOutput],
+    ) -> Option<f64> {
+        let consumed = self.inputs_sum_rate(inputs);
+        let kcal_per_s = outputs
+            .iter()
+            .filter(|o| o.recipe_id == self.id)
+            .filter_map(|o| o.calories_kcal.map(|kcal| kcal * o.rate_kg_per_s))
+            .fold(None, |total: Option<f64>, kcal| {
+                Some(total.unwrap_or(0.0) + kcal)
+            });
+        kcal_per_s
+            .filter(|_| consumed > 0.0)
+            .map(|kcal| kcal / consumed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct RecipeInput {
     pub recipe_id: i64,
     pub resource_id: String,
     pub rate_kg_per_s: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RecipeOutput {
     pub recipe_id: i64,
     pub resource_id: String,
     pub rate_kg_per_s: f64,
+    pub calories_kcal: Option<f64>, // Per kg of this output, for food
+}
+
+/// Replacement rate for one building input or output, used by the calculator
+/// in place of the extracted rate
+#[derive(Debug, Clone)]
+pub struct RateOverride {
+    pub building_id: String,
+    pub resource_id: String,
+    pub direction: String, // "input" or "output"
+    pub rate_kg_per_s: f64,
+}
+
+/// Building stat that a `HypotheticalOverride` shadows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HypotheticalField {
+    PowerWatts,
+    OutputRate,
+}
+
+impl HypotheticalField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HypotheticalField::PowerWatts => "power",
+            HypotheticalField::OutputRate => "output rate",
+        }
+    }
+}
+
+/// Stand-in value for one building stat, used for a single calculation
+/// without touching the database
+#[derive(Debug, Clone)]
+pub struct HypotheticalOverride {
+    pub building_id: String,
+    pub field: HypotheticalField,
+    pub value: f64,
+}
+
+/// How `db::get_producers_ordered_by` sorts the buildings making a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProducerOrder {
+    /// Highest output rate per building first
+    Rate,
+    /// Least power per kg/s produced first (generators before consumers)
+    PowerEfficiency,
+    /// Least heat per kg/s produced first
+    HeatOutput,
+    /// By building name
+    Alphabetical,
+}
+
+/// Restricts a building set (export commands, named views)
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct BuildingFilter {
+    /// Only export buildings in this category (case-insensitive)
+    #[arg(long = "filter-category")]
+    pub category: Option<String>,
+
+    /// Only export buildings from this DLC ("base" for the base game)
+    #[arg(long = "filter-dlc")]
+    pub dlc: Option<String>,
+
+    /// Only export buildings whose name contains this text
+    #[arg(long = "filter-name")]
+    pub name_contains: Option<String>,
+
+    /// Only export buildings written after this UTC time (e.g. "2024-01-01T00:00:00Z")
+    #[arg(long)]
+    pub since: Option<String>,
+}
+
+/// Aggregate power figures across all buildings
+#[derive(Debug, Clone, Default)]
+pub struct EfficiencyStats {
+    pub min_power_w: f64,
+    pub max_power_w: f64,
+    pub mean_power_w: f64,
+    pub total_building_count: usize,
+}
+
+/// Database-wide supply and demand per resource, summed over every building's rates
+///
+/// Rates are `(resource_id, supply - demand)` in kg/s, largest imbalance first.
+#[derive(Debug, Clone, Default)]
+pub struct FlowAnalysis {
+    pub over_supplied: Vec<(String, f64)>,
+    pub under_supplied: Vec<(String, f64)>,
+    pub balanced: Vec<String>,
+}
+
+/// A building with everything stored about it, for single-building display
+///
+/// `{}` renders plain indented text; `{:#}` adds ASCII table borders.
+#[derive(Debug, Clone, Serialize)]
+pub struct FullBuilding {
+    pub building: Building,
+    pub description: Option<String>,
+    pub inputs: Vec<BuildingInput>,
+    pub outputs: Vec<BuildingOutput>,
+    pub materials: Vec<(String, f64)>, // (resource_id, mass_kg)
+    pub recipes: Vec<RecipeWithIo>,
 }
 
+impl fmt::Display for FullBuilding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let borders = f.alternate();
+        let b = &self.building;
+
+        writeln!(f, "Building: {}", b.name)?;
+        writeln!(f, "  ID: {}", b.id)?;
+        writeln!(f, "  Type: {}", b.building_type.as_str())?;
+        if let Some(category) = &b.category {
+            writeln!(f, "  Category: {}", category)?;
+        }
+        if let Some(dlc) = &b.dlc {
+            writeln!(f, "  DLC: {}", dlc)?;
+        }
+        writeln!(f, "  Power: {}W", b.power_watts)?;
+        writeln!(f, "  Heat: {} DTU/s", b.heat_output_dtu)?;
+        if let Some(time) = b.construction_time_s {
+            writeln!(f, "  Construction: {}s", time)?;
+        }
+        if let Some(critters) = b.min_critters {
+            writeln!(f, "  Critters: {}", critters)?;
+        }
+        if b.extractor {
+            writeln!(f, "  Extractor: yes")?;
+        }
+        if b.dupe_operated {
+            writeln!(f, "  Dupe Operated: yes")?;
+        }
+        if let Some(description) = &self.description {
+            writeln!(f, "  Description: {}", description)?;
+        }
+
+        let flow_rows = |flows: Vec<(&String, f64)>| -> Vec<(String, String)> {
+            flows
+                .into_iter()
+                .map(|(resource, rate)| (resource.clone(), format!("{:.4}", rate)))
+                .collect()
+        };
+
+        let inputs = flow_rows(
+            self.inputs
+                .iter()
+                .map(|i| (&i.resource_id, i.rate_kg_per_s))
+                .collect(),
+        );
+        write_table(f, "Inputs", ("Resource", "kg/s"), &inputs, borders)?;
+
+        let outputs: Vec<_> = self
+            .outputs
+            .iter()
+            .map(|o| match o.output_type {
+                BuildingOutputType::Mass => {
+                    (o.resource_id.clone(), format!("{:.4}", o.rate_kg_per_s))
+                }
+                other => (
+                    format!("{} ({})", o.resource_id, other.unit()),
+                    format!("{:.4}", o.rate_kg_per_s),
+                ),
+            })
+            .collect();
+        write_table(f, "Outputs", ("Resource", "kg/s"), &outputs, borders)?;
+
+        let materials: Vec<_> = self
+            .materials
+            .iter()
+            .map(|(resource, mass)| (resource.clone(), format!("{:.0}", mass)))
+            .collect();
+        write_table(f, "Materials", ("Resource", "kg"), &materials, borders)?;
+
+        for (recipe, inputs, outputs) in &self.recipes {
+            let mut rows = flow_rows(
+                inputs
+                    .iter()
+                    .map(|i| (&i.resource_id, -i.rate_kg_per_s))
+                    .collect(),
+            );
+            rows.extend(flow_rows(
+                outputs
+                    .iter()
+                    .map(|o| (&o.resource_id, o.rate_kg_per_s))
+                    .collect(),
+            ));
+            let title = match recipe.cycle_time_s {
+                Some(time) => format!("Recipe {} ({}s)", recipe.name, time),
+                None => format!("Recipe {}", recipe.name),
+            };
+            write_table(f, &title, ("Resource", "kg/s"), &rows, borders)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write a two-column table, skipped entirely when there are no rows
+fn write_table(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    headers: (&str, &str),
+    rows: &[(String, String)],
+    borders: bool,
+) -> fmt::Result {
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let left = rows
+        .iter()
+        .map(|r| r.0.len())
+        .chain([headers.0.len()])
+        .max()
+        .unwrap_or(0);
+    let right = rows
+        .iter()
+        .map(|r| r.1.len())
+        .chain([headers.1.len()])
+        .max()
+        .unwrap_or(0);
+
+    writeln!(f, "  {}:", title)?;
+    if borders {
+        let rule = format!("    +-{}-+-{}-+", "-".repeat(left), "-".repeat(right));
+        writeln!(f, "{}", rule)?;
+        writeln!(f, "    | {:<left$} | {:>right$} |", headers.0, headers.1)?;
+        writeln!(f, "{}", rule)?;
+        for (l, r) in rows {
+            writeln!(f, "    | {:<left$} | {:>right$} |", l, r)?;
+        }
+        writeln!(f, "{}", rule)?;
+    } else {
+        for (l, r) in rows {
+            writeln!(f, "    {:<left$}  {:>right$}", l, r)?;
+        }
+    }
+    Ok(())
+}
+
+/// Field-by-field differences between two buildings
+#[derive(Debug, Clone)]
+pub struct BuildingComparison {
+    pub id_a: String,
+    pub id_b: String,
+    pub differences: Vec<FieldDifference>,
+}
+
+/// One differing field; `None` means the building doesn't have it (e.g. no such input)
+#[derive(Debug, Clone)]
+pub struct FieldDifference {
+    pub field: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+impl fmt::Display for BuildingComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.differences.is_empty() {
+            return writeln!(f, "{} and {} are identical", self.id_a, self.id_b);
+        }
+
+        let width = self
+            .differences
+            .iter()
+            .map(|d| d.field.len())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        writeln!(
+            f,
+            "{:<width$}  {:>20}  {:>20}",
+            "Field", self.id_a, self.id_b
+        )?;
+        writeln!(f, "{}", "-".repeat(width + 44))?;
+        for d in &self.differences {
+            writeln!(
+                f,
+                "{:<width$}  {:>20}  {:>20}",
+                d.field,
+                d.a.as_deref().unwrap_or("-"),
+                d.b.as_deref().unwrap_or("-")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What `db::merge_database` does with a building both databases have in different versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergeConflictMode {
+    /// Keep the destination's version
+    Skip,
+    /// Replace it with the source's version
+    Overwrite,
+    /// Keep the destination's version and list both in the merge report
+    Report,
+}
+
+/// A building whose source and destination versions differ
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    pub building_id: String,
+    pub source: FullBuilding,
+    pub destination: FullBuilding,
+}
+
+/// Outcome of `db::merge_database`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    pub conflicts: Vec<MergeConflict>, // Only collected with `MergeConflictMode::Report`
+    pub merged_count: usize,
+}
+
+/// Errors from production chain calculation
+#[derive(Debug, Error)]
+pub enum ProductionChainError {
+    #[error("no building produces {resource}")]
+    NoProducers { resource: String },
+
+    #[error("{building} produces {resource} at {rate} kg/s")]
+    InvalidOutputRate {
+        building: String,
+        resource: String,
+        rate: f64,
+    },
+
+    #[error("database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+
+    /// Any other failure reported by a `db` function
+    #[error("{0}")]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ProductionChainError {
+    /// `db` functions return `anyhow` errors; SQLite ones become `DatabaseError`
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<rusqlite::Error>() {
+            Ok(e) => ProductionChainError::DatabaseError(e),
+            Err(other) => ProductionChainError::Other(other),
+        }
+    }
+}
+
+/// Well-known `ProductionNode::metadata` keys
+pub const METADATA_CATEGORY: &str = "category";
+pub const METADATA_DLC: &str = "dlc";
+pub const METADATA_OVERRIDE: &str = "override";
+pub const METADATA_CRITTERS: &str = "critters";
+pub const METADATA_BYPRODUCT_FUEL: &str = "byproduct fuel";
+pub const METADATA_DUPE_OPERATED: &str = "dupe operated";
+pub const METADATA_MERGED: &str = "merged into";
+
 /// Result of a production chain calculation
 #[derive(Debug, Clone)]
 pub struct ProductionNode {
     pub building_id: String,
     pub building_name: String,
     pub count: f64,
+    pub raw_count: f64, // Buildings needed before rounding up; equals `count` without round_up
+    pub output_rate: f64, // Per-building rate of the resource this node produces
     pub power_watts: f64,
+    pub heat_dtu: f64,          // Heat output of all buildings in this node, DTU/s
+    pub recipe: Option<String>, // Recipe the buildings run, if chosen with calculate_production_chain_with_recipe
     pub inputs: Vec<InputRequirement>,
+    pub metadata: HashMap<String, String>, // Extra annotations, see METADATA_* keys
+}
+
+impl ProductionNode {
+    /// Whether this node is an actual building rather than a raw input or cycle break
+    pub fn is_building(&self) -> bool {
+        self.building_id != RAW_RESOURCE_ID && self.building_id != CYCLE_BREAK_ID
+    }
+
+    /// Copy of this chain with every building count, power and heat figure and input rate
+    /// multiplied by `factor` (per-building `output_rate` is unchanged)
+    pub fn scale(&self, factor: f64) -> ProductionNode {
+        ProductionNode {
+            building_id: self.building_id.clone(),
+            building_name: self.building_name.clone(),
+            count: self.count * factor,
+            raw_count: self.raw_count * factor,
+            output_rate: self.output_rate,
+            power_watts: self.power_watts * factor,
+            heat_dtu: self.heat_dtu * factor,
+            recipe: self.recipe.clone(),
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| InputRequirement {
+                    resource_id: input.resource_id.clone(),
+                    rate_kg_per_s: input.rate_kg_per_s * factor,
+                    surplus_rate: input.surplus_rate * factor,
+                    upstream: input
+                        .upstream
+                        .as_ref()
+                        .map(|node| Box::new(node.scale(factor))),
+                })
+                .collect(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Whole buildings needed for the chain: every node's count rounded up
+    pub fn total_buildings_ceiling(&self) -> f64 {
+        self.iter()
+            .filter(|n| n.is_building())
+            .map(|n| n.count.ceil())
+            .sum()
+    }
+
+    /// Combine sibling nodes built from the same building, throughout the chain
+    ///
+    /// The first sibling takes the others' counts, power, heat and inputs
+    /// (inputs with the same resource are summed into one). The others stay as
+    /// empty placeholders annotated with `METADATA_MERGED`, so their inputs
+    /// still count as produced in the chain. With `round_up` the combined count
+    /// is the combined raw count rounded up, not the sum of rounded counts.
+    pub fn merge_duplicates(&mut self, round_up: bool) {
+        for i in 1..self.inputs.len() {
+            let (earlier, rest) = self.inputs.split_at_mut(i);
+            let current = &mut rest[0];
+            let Some(node) = current.upstream.as_deref().filter(|n| n.is_building()) else {
+                continue;
+            };
+            let Some(target_input) = earlier.iter_mut().find(|input| {
+                input
+                    .upstream
+                    .as_deref()
+                    .is_some_and(|n| n.building_id == node.building_id && n.count > 0.0)
+            }) else {
+                continue;
+            };
+            let Some(target) = target_input.upstream.as_deref_mut() else {
+                continue;
+            };
+
+            let mut metadata = HashMap::new();
+            metadata.insert(
+                METADATA_MERGED.to_string(),
+                format!("{} above", target.building_name),
+            );
+            let placeholder = ProductionNode {
+                building_id: node.building_id.clone(),
+                building_name: node.building_name.clone(),
+                count: 0.0,
+                raw_count: 0.0,
+                output_rate: node.output_rate,
+                power_watts: 0.0,
+                heat_dtu: 0.0,
+                recipe: None,
+                inputs: Vec::new(),
+                metadata,
+            };
+            if let Some(node) = current.upstream.replace(Box::new(placeholder)) {
+                target.absorb(*node, round_up);
+            }
+
+            // The combined buildings now supply both inputs, so any surplus is counted once
+            if target_input.resource_id == current.resource_id {
+                let demand = target_input.rate_kg_per_s + current.rate_kg_per_s;
+                target_input.surplus_rate = (target.count * target.output_rate - demand).max(0.0);
+                current.surplus_rate = 0.0;
+            }
+        }
+
+        for input in &mut self.inputs {
+            if let Some(upstream) = input.upstream.as_deref_mut() {
+                upstream.merge_duplicates(round_up);
+            }
+        }
+    }
+
+    /// Add `other`'s counts, power, heat and inputs to this node
+    ///
+    /// Power and heat follow the recounted buildings. Inputs for the same
+    /// resource are summed when their upstream nodes use the same building
+    /// (merging those nodes too); an input supplied by a different building,
+    /// e.g. one cut off by a cycle on only one side, is kept as a separate input.
+    fn absorb(&mut self, other: ProductionNode, round_up: bool) {
+        let (unit_power, unit_heat) = [&*self, &other]
+            .into_iter()
+            .find(|n| n.count > 0.0)
+            .map(|n| (n.power_watts / n.count, n.heat_dtu / n.count))
+            .unwrap_or_default();
+        self.raw_count += other.raw_count;
+        self.count = if round_up {
+            self.raw_count.ceil()
+        } else {
+            self.raw_count
+        };
+        self.power_watts = unit_power * self.count;
+        self.heat_dtu = unit_heat * self.count;
+
+        for input in other.inputs {
+            let existing = self.inputs.iter_mut().find(|i| {
+                i.resource_id == input.resource_id
+                    && match (i.upstream.as_deref(), input.upstream.as_deref()) {
+                        // A merged placeholder (count 0) takes nothing more
+                        (Some(a), Some(b)) => {
+                            a.building_id == b.building_id && (a.count > 0.0 || !a.is_building())
+                        }
+                        _ => true,
+                    }
+            });
+            let Some(existing) = existing else {
+                self.inputs.push(input);
+                continue;
+            };
+            existing.rate_kg_per_s += input.rate_kg_per_s;
+            existing.surplus_rate += input.surplus_rate;
+            match (existing.upstream.as_deref_mut(), input.upstream) {
+                (Some(a), Some(b)) => {
+                    a.absorb(*b, round_up);
+                    if a.is_building() {
+                        existing.surplus_rate =
+                            (a.count * a.output_rate - existing.rate_kg_per_s).max(0.0);
+                    }
+                }
+                (None, Some(b)) => existing.upstream = Some(b),
+                _ => {}
+            }
+        }
+    }
+
+    /// Inputs not made by a building in the chain: raw-resource nodes, or
+    /// inputs cut off by a cycle
+    pub fn filter_raw_inputs(&self) -> Vec<&InputRequirement> {
+        self.inputs.iter().filter(|i| !i.is_produced()).collect()
+    }
+
+    /// Inputs made by an upstream building in the chain
+    pub fn filter_produced_inputs(&self) -> Vec<&InputRequirement> {
+        self.inputs.iter().filter(|i| i.is_produced()).collect()
+    }
+
+    /// Depth-first (pre-order) iterator over this node and everything upstream of it
+    pub fn iter(&self) -> ProductionNodeIter<'_> {
+        ProductionNodeIter { stack: vec![self] }
+    }
+}
+
+/// Iterator returned by `ProductionNode::iter`
+pub struct ProductionNodeIter<'a> {
+    stack: Vec<&'a ProductionNode>,
+}
+
+impl<'a> Iterator for ProductionNodeIter<'a> {
+    type Item = &'a ProductionNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // Push in reverse so inputs are visited in their listed order
+        self.stack.extend(
+            node.inputs
+                .iter()
+                .rev()
+                .filter_map(|i| i.upstream.as_deref()),
+        );
+        Some(node)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct InputRequirement {
     pub resource_id: String,
     pub rate_kg_per_s: f64,
+    /// Upstream production beyond `rate_kg_per_s`, e.g. from rounding buildings up
+    pub surplus_rate: f64,
     pub upstream: Option<Box<ProductionNode>>,
 }
+
+impl InputRequirement {
+    /// Fraction of the upstream production this input actually uses (1.0 = no surplus)
+    pub fn utilization(&self) -> f64 {
+        let available = self.rate_kg_per_s + self.surplus_rate;
+        if available > 0.0 {
+            self.rate_kg_per_s / available
+        } else {
+            1.0
+        }
+    }
+
+    /// Whether a building upstream in the chain makes this input
+    pub fn is_produced(&self) -> bool {
+        self.upstream.as_ref().is_some_and(|u| u.is_building())
+    }
+}
+
+/// Differences between two calculated chains, e.g. before and after a game patch
+///
+/// Buildings are compared by ID with their counts summed across the chain.
+#[derive(Debug, Clone, Default)]
+pub struct ProductionNodeDiff {
+    pub added_buildings: Vec<String>,
+    pub removed_buildings: Vec<String>,
+    pub changed_counts: Vec<(String, f64, f64)>, // (building_id, old_count, new_count)
+    pub power_delta: f64,                        // New net power minus old, in W
+}
+
+impl ProductionNodeDiff {
+    pub fn compute(old: &ProductionNode, new: &ProductionNode) -> Self {
+        // Sorted so the diff lists buildings in a stable order
+        let counts = |root: &ProductionNode| {
+            let mut counts: Vec<(String, f64)> = Vec::new();
+            for node in root.iter().filter(|n| n.is_building()) {
+                match counts.iter_mut().find(|(id, _)| *id == node.building_id) {
+                    Some(entry) => entry.1 += node.count,
+                    None => counts.push((node.building_id.clone(), node.count)),
+                }
+            }
+            counts.sort_by(|a, b| a.0.cmp(&b.0));
+            counts
+        };
+        let old_counts = counts(old);
+        let new_counts = counts(new);
+        let find = |counts: &[(String, f64)], id: &str| {
+            counts.iter().find(|(other, _)| other == id).map(|c| c.1)
+        };
+
+        let mut diff = ProductionNodeDiff::default();
+        for (id, old_count) in &old_counts {
+            match find(&new_counts, id) {
+                None => diff.removed_buildings.push(id.clone()),
+                Some(new_count) if (new_count - old_count).abs() > 1e-9 => diff
+                    .changed_counts
+                    .push((id.clone(), *old_count, new_count)),
+                Some(_) => {}
+            }
+        }
+        for (id, _) in &new_counts {
+            if find(&old_counts, id).is_none() {
+                diff.added_buildings.push(id.clone());
+            }
+        }
+
+        let net_power = |root: &ProductionNode| root.iter().map(|n| n.power_watts).sum::<f64>();
+        diff.power_delta = net_power(new) - net_power(old);
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_buildings.is_empty()
+            && self.removed_buildings.is_empty()
+            && self.changed_counts.is_empty()
+            && self.power_delta.abs() < 1e-9
+    }
+}
+
+impl fmt::Display for ProductionNodeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for id in &self.added_buildings {
+            writeln!(f, "  + {}", id)?;
+        }
+        for id in &self.removed_buildings {
+            writeln!(f, "  - {}", id)?;
+        }
+        for (id, old, new) in &self.changed_counts {
+            writeln!(f, "  ~ {}: {:.2}x -> {:.2}x", id, old, new)?;
+        }
+        writeln!(f, "  Power: {:+.0}W", self.power_delta)
+    }
+}
+
+/// The root of a calculated production chain, printable as an indented tree
+#[derive(Debug, Clone)]
+pub struct ProductionTree(pub ProductionNode);
+
+impl ProductionTree {
+    /// One-line summary: building counts in chain order plus net power
+    pub fn format_compact(&self) -> String {
+        let mut buildings: Vec<(&str, f64)> = Vec::new();
+        let mut raw_inputs: Vec<&str> = Vec::new();
+        let mut net_power = 0.0;
+
+        for node in self.0.iter().filter(|n| n.is_building()) {
+            net_power += node.power_watts;
+            for input in node.filter_raw_inputs() {
+                if !raw_inputs.contains(&input.resource_id.as_str()) {
+                    raw_inputs.push(&input.resource_id);
+                }
+            }
+            if let Some(entry) = buildings
+                .iter_mut()
+                .find(|(name, _)| *name == node.building_name)
+            {
+                entry.1 += node.count;
+            } else {
+                buildings.push((&node.building_name, node.count));
+            }
+        }
+
+        let parts: Vec<String> = buildings
+            .iter()
+            .map(|(name, count)| format!("{:.2}x {}", count, name))
+            .collect();
+        format!(
+            "{} ({:.0}W net, {} raw inputs)",
+            parts.join(" + "),
+            net_power,
+            raw_inputs.len()
+        )
+    }
+}
+
+impl fmt::Display for ProductionTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let output = crate::calculator::format_production_chain(&self.0, 0, &HashMap::new());
+        write!(f, "{}", output)
+    }
+}
+
+/// Independent production chains calculated together, one root per target
+#[derive(Debug, Clone)]
+pub struct ProductionForest {
+    pub roots: Vec<ProductionNode>,
+    pub targets: Vec<(String, f64)>, // (resource_id, rate) of each root, same order
+}
+
+impl ProductionForest {
+    /// Copy of the forest with every chain and target rate multiplied by `factor`
+    pub fn scale(&self, factor: f64) -> ProductionForest {
+        ProductionForest {
+            roots: self.roots.iter().map(|root| root.scale(factor)).collect(),
+            targets: self
+                .targets
+                .iter()
+                .map(|(resource, rate)| (resource.clone(), rate * factor))
+                .collect(),
+        }
+    }
+
+    /// Net power of all chains in W (negative = consumption)
+    pub fn total_power(&self) -> f64 {
+        self.roots.iter().map(crate::calculator::total_power).sum()
+    }
+
+    /// Heat output of all chains in DTU/s
+    pub fn total_heat(&self) -> f64 {
+        self.roots.iter().map(crate::calculator::total_heat).sum()
+    }
+
+    /// Combined summary of all chains
+    pub fn summarize(&self) -> crate::calculator::ChainSummary {
+        let summaries: Vec<_> = self
+            .roots
+            .iter()
+            .zip(&self.targets)
+            .map(|(root, (resource, rate))| {
+                crate::calculator::summarize_chain(root, resource, *rate)
+            })
+            .collect();
+        crate::calculator::merge_summaries(&summaries)
+    }
+
+    /// Every chain as an indented tree, headed by its target when there are several
+    pub fn format_all(&self) -> String {
+        let mut output = String::new();
+        for (root, (resource, rate)) in self.roots.iter().zip(&self.targets) {
+            if self.roots.len() > 1 {
+                output.push_str(&format!("--- {} @ {:.3} kg/s ---\n", resource, rate));
+            }
+            output.push_str(&crate::calculator::format_production_chain(
+                root,
+                0,
+                &HashMap::new(),
+            ));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A node of `raw_count` buildings drawing 100 W each, optionally rounded up
+    fn node(
+        building_id: &str,
+        raw_count: f64,
+        round_up: bool,
+        inputs: Vec<InputRequirement>,
+    ) -> ProductionNode {
+        let count = if round_up {
+            raw_count.ceil()
+        } else {
+            raw_count
+        };
+        ProductionNode {
+            building_id: building_id.to_string(),
+            building_name: building_id.to_string(),
+            count,
+            raw_count,
+            output_rate: 1.0,
+            power_watts: -100.0 * count,
+            heat_dtu: 10.0 * count,
+            recipe: None,
+            inputs,
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn input(resource_id: &str, rate: f64, upstream: ProductionNode) -> InputRequirement {
+        InputRequirement {
+            resource_id: resource_id.to_string(),
+            rate_kg_per_s: rate,
+            surplus_rate: 0.0,
+            upstream: Some(Box::new(upstream)),
+        }
+    }
+
+    #[test]
+    fn merged_count_is_rounded_once() {
+        let mut root = node(
+            "Root",
+            1.0,
+            true,
+            vec![
+                input("Oxygen", 0.4, node("Electrolyzer", 0.4, true, Vec::new())),
+                input("Oxygen", 0.3, node("Electrolyzer", 0.3, true, Vec::new())),
+            ],
+        );
+        root.merge_duplicates(true);
+
+        let merged = root.inputs[0].upstream.as_deref().unwrap();
+        assert_eq!(merged.count, 1.0);
+        assert!((merged.raw_count - 0.7).abs() < 1e-9);
+        assert_eq!(merged.power_watts, -100.0);
+        assert_eq!(merged.heat_dtu, 10.0);
+        assert!((root.inputs[0].surplus_rate - 0.3).abs() < 1e-9);
+
+        let placeholder = root.inputs[1].upstream.as_deref().unwrap();
+        assert_eq!(placeholder.count, 0.0);
+        assert!(placeholder.metadata.contains_key(METADATA_MERGED));
+    }
+
+    #[test]
+    fn inputs_from_different_buildings_are_kept() {
+        let cycle = ProductionNode {
+            building_id: CYCLE_BREAK_ID.to_string(),
+            building_name: "Water (cycle)".to_string(),
+            count: 0.0,
+            raw_count: 0.0,
+            output_rate: 0.0,
+            power_watts: 0.0,
+            heat_dtu: 0.0,
+            recipe: None,
+            inputs: Vec::new(),
+            metadata: HashMap::new(),
+        };
+        let first = node("Electrolyzer", 1.0, false, vec![input("Water", 1.0, cycle)]);
+        let sieve = node("WaterSieve", 0.2, false, Vec::new());
+        let second = node("Electrolyzer", 1.0, false, vec![input("Water", 1.0, sieve)]);
+        let mut root = node(
+            "Root",
+            1.0,
+            false,
+            vec![input("Oxygen", 1.0, first), input("Oxygen", 1.0, second)],
+        );
+        root.merge_duplicates(false);
+
+        let merged = root.inputs[0].upstream.as_deref().unwrap();
+        assert_eq!(merged.count, 2.0);
+        assert_eq!(merged.inputs.len(), 2);
+        let sieve = merged
+            .inputs
+            .iter()
+            .filter_map(|i| i.upstream.as_deref())
+            .find(|n| n.building_id == "WaterSieve");
+        assert_eq!(sieve.map(|n| n.count), Some(0.2));
+        // Root, both Electrolyzers and the Water Sieve all still draw power
+        assert_eq!(
+            root.iter().map(|n| n.power_watts).sum::<f64>(),
+            -100.0 - 200.0 - 20.0
+        );
+    }
+}
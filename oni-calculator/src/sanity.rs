@@ -0,0 +1,115 @@
+//! Cross-check the database against known-good ONI values
+//!
+//! The reference values are bundled into the binary so a bad extraction
+//! shows up without the user needing to know the correct rates.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::db;
+
+/// Ground-truth rates for a handful of key buildings
+const REFERENCE_JSON: &[u8] = include_bytes!("reference.json");
+
+/// Relative difference above which a value is reported
+pub const TOLERANCE: f64 = 0.05;
+
+#[derive(Debug, Deserialize)]
+struct Reference {
+    buildings: Vec<ReferenceBuilding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReferenceBuilding {
+    id: String,
+    power_watts: f64,
+    inputs: BTreeMap<String, f64>,
+    outputs: BTreeMap<String, f64>,
+}
+
+/// Result of checking one reference building
+#[derive(Debug)]
+pub struct SanityResult {
+    pub building_id: String,
+    pub issues: Vec<String>,
+}
+
+/// Compare every reference building against the database
+pub fn check_reference(conn: &Connection) -> Result<Vec<SanityResult>> {
+    let reference: Reference = serde_json::from_slice(REFERENCE_JSON)?;
+
+    let mut results = Vec::new();
+    for expected in reference.buildings {
+        let mut issues = Vec::new();
+
+        match db::get_full_building(conn, &expected.id)? {
+            None => issues.push("not in database".to_string()),
+            Some(actual) => {
+                if deviates(actual.building.power_watts, expected.power_watts) {
+                    issues.push(format!(
+                        "power {}W, expected {}W",
+                        actual.building.power_watts, expected.power_watts
+                    ));
+                }
+
+                let inputs: Vec<_> = actual
+                    .inputs
+                    .iter()
+                    .map(|i| (&i.resource_id, i.rate_kg_per_s))
+                    .collect();
+                compare_flows("input", &inputs, &expected.inputs, &mut issues);
+
+                let outputs: Vec<_> = actual
+                    .outputs
+                    .iter()
+                    .map(|o| (&o.resource_id, o.rate_kg_per_s))
+                    .collect();
+                compare_flows("output", &outputs, &expected.outputs, &mut issues);
+            }
+        }
+
+        results.push(SanityResult {
+            building_id: expected.id,
+            issues,
+        });
+    }
+
+    Ok(results)
+}
+
+fn compare_flows(
+    direction: &str,
+    actual: &[(&String, f64)],
+    expected: &BTreeMap<String, f64>,
+    issues: &mut Vec<String>,
+) {
+    for (resource, expected_rate) in expected {
+        match actual.iter().find(|(r, _)| *r == resource) {
+            None => issues.push(format!("missing {} {}", direction, resource)),
+            Some((_, rate)) if deviates(*rate, *expected_rate) => issues.push(format!(
+                "{} {} @ {} kg/s, expected {} kg/s",
+                direction, resource, rate, expected_rate
+            )),
+            Some(_) => {}
+        }
+    }
+    for (resource, rate) in actual {
+        if !expected.contains_key(*resource) {
+            issues.push(format!(
+                "unexpected {} {} @ {} kg/s",
+                direction, resource, rate
+            ));
+        }
+    }
+}
+
+pub fn deviates(actual: f64, expected: f64) -> bool {
+    if expected == 0.0 {
+        actual.abs() > f64::EPSILON
+    } else {
+        ((actual - expected) / expected).abs() > TOLERANCE
+    }
+}
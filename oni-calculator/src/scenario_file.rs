@@ -0,0 +1,42 @@
+//! Plain-text scenario files: one `RESOURCE RATE` target per line
+
+use std::io::BufRead;
+
+use anyhow::{Context, Result, anyhow};
+
+/// Parse production targets from a scenario file
+///
+/// Blank lines and anything after `#` are ignored, e.g.
+///
+/// ```text
+/// # Base needs
+/// Oxygen 0.5
+/// Water  2.0   # for the farms
+/// ```
+pub fn parse(reader: impl BufRead) -> Result<Vec<(String, f64)>> {
+    let mut targets = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let content = line.split('#').next().unwrap_or("").trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = content.split_whitespace().collect();
+        let [resource, rate] = fields[..] else {
+            return Err(anyhow!(
+                "line {}: expected 'RESOURCE RATE', got '{}'",
+                line_number,
+                content
+            ));
+        };
+        let rate: f64 = rate
+            .parse()
+            .with_context(|| format!("line {}: invalid rate '{}'", line_number, rate))?;
+        targets.push((resource.to_string(), rate));
+    }
+
+    Ok(targets)
+}
@@ -0,0 +1,50 @@
+//! Check GitHub for a newer release of the calculator
+
+use std::time::Duration;
+
+use anyhow::Result;
+
+/// GitHub API endpoint for the latest published release
+#[cfg_attr(not(feature = "update-check"), allow(dead_code))]
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/lawless-m/StinkySpy/releases/latest";
+
+/// Fetch the tag name of the latest release (e.g. `"v0.2.0"`)
+#[cfg(feature = "update-check")]
+pub fn latest_release_tag(timeout: Duration) -> Result<String> {
+    #[derive(serde::Deserialize)]
+    struct Release {
+        tag_name: String,
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("oni-calculator/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let release: Release = client
+        .get(LATEST_RELEASE_URL)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(release.tag_name)
+}
+
+/// Fetch the tag name of the latest release (e.g. `"v0.2.0"`)
+#[cfg(not(feature = "update-check"))]
+pub fn latest_release_tag(_timeout: Duration) -> Result<String> {
+    anyhow::bail!("built without the update-check feature")
+}
+
+/// Whether release tag `latest` is a higher version than `current`
+///
+/// Compares dot-separated numeric components, ignoring a leading `v`;
+/// non-numeric components count as 0.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parts(latest) > parts(current)
+}